@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::inventory::Inventory;
+use crate::weapon::WeaponId;
+use crate::weapon_definition::{WeaponDefinition, WeaponRegistry};
+
+/// Munitions d'une arme. Le serveur tient le compte qui fait foi, le client predit le sien.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ammo {
+    pub magazine: u32,
+    pub reserve: u32,
+}
+
+impl Ammo {
+    /// Chargeur plein et reserve de depart, a l'apparition
+    pub fn full(definition: &WeaponDefinition) -> Self {
+        Ammo {
+            magazine: definition.magazine_size,
+            reserve: definition.reserve_ammo,
+        }
+    }
+
+    /// Retire une balle du chargeur, `false` s'il est vide
+    pub fn try_fire(&mut self) -> bool {
+        if self.magazine == 0 {
+            return false;
+        }
+        self.magazine -= 1;
+        true
+    }
+
+    pub fn can_reload(&self, definition: &WeaponDefinition) -> bool {
+        self.magazine < definition.magazine_size && self.reserve > 0
+    }
+
+    /// Remplit le chargeur avec ce qui reste en reserve
+    pub fn reload(&mut self, definition: &WeaponDefinition) {
+        let moved = definition
+            .magazine_size
+            .saturating_sub(self.magazine)
+            .min(self.reserve);
+        self.magazine += moved;
+        self.reserve -= moved;
+    }
+}
+
+/// Munitions predites du joueur local, remplacees par chaque `AmmoChanged` du serveur
+#[derive(Debug, Default, Resource)]
+pub struct LocalAmmo {
+    ammo: HashMap<WeaponId, Ammo>,
+    // Rechargement en cours et son avancement
+    pub reload: Option<(WeaponId, Timer)>,
+}
+
+impl LocalAmmo {
+    /// Une arme jamais utilisee depuis l'apparition a ses munitions de depart
+    pub fn get(&self, definition: &WeaponDefinition) -> Ammo {
+        self.ammo
+            .get(&definition.id)
+            .copied()
+            .unwrap_or_else(|| Ammo::full(definition))
+    }
+
+    pub fn get_mut(&mut self, definition: &WeaponDefinition) -> &mut Ammo {
+        self.ammo
+            .entry(definition.id)
+            .or_insert_with(|| Ammo::full(definition))
+    }
+
+    pub fn set(&mut self, weapon: WeaponId, ammo: Ammo) {
+        self.ammo.insert(weapon, ammo);
+    }
+
+    /// Au respawn : tout est plein et le rechargement est annule
+    pub fn reset(&mut self) {
+        *self = LocalAmmo::default();
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload.is_some()
+    }
+
+    /// Avancement du rechargement entre 0 et 1, pour l'animation du bras
+    pub fn reload_progress(&self) -> Option<f32> {
+        self.reload.as_ref().map(|(_, timer)| timer.fraction())
+    }
+}
+
+/// Termine le rechargement local, le serveur le termine de son cote au meme moment
+pub fn update_reload(
+    time: Res<Time>,
+    weapons: Res<WeaponRegistry>,
+    mut local_ammo: ResMut<LocalAmmo>,
+) {
+    let Some((weapon, timer)) = local_ammo.reload.as_mut() else {
+        return;
+    };
+    let weapon = *weapon;
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+    local_ammo.reload = None;
+    if let Some(definition) = weapons.get(weapon) {
+        local_ammo.get_mut(definition).reload(definition);
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct AmmoText;
+
+pub fn spawn_ammo_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            right: Val::Px(20.0),
+            ..default()
+        }),
+        AmmoText,
+    ));
+}
+
+pub fn update_ammo_hud(
+    weapons: Res<WeaponRegistry>,
+    local_ammo: Res<LocalAmmo>,
+    inventory: Res<Inventory>,
+    mut text: Query<&mut Text, With<AmmoText>>,
+) {
+    let (Some(definition), Ok(mut text)) = (weapons.get(inventory.weapon()), text.get_single_mut())
+    else {
+        return;
+    };
+    let ammo = local_ammo.get(definition);
+    let value = if local_ammo.is_reloading() {
+        format!(
+            "{}   Reloading...   {} / {}",
+            definition.name, ammo.magazine, ammo.reserve
+        )
+    } else {
+        format!("{}   {} / {}", definition.name, ammo.magazine, ammo.reserve)
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
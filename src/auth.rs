@@ -0,0 +1,196 @@
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Duration, SystemTime},
+};
+
+use rand::Rng;
+use renet::transport::{generate_random_bytes, ConnectToken, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES};
+
+use crate::connection::PROTOCOL_ID;
+use crate::server::ShutdownSignal;
+use crate::username::UserData;
+
+/// Cle partagee entre le serveur de jeu et le serveur de tokens
+pub type PrivateKey = [u8; NETCODE_KEY_BYTES];
+
+/// Mot de passe de la partie privee, complete par des zeros
+pub const PASSWORD_BYTES: usize = 64;
+pub type MatchPassword = [u8; PASSWORD_BYTES];
+
+// Premier octet de la reponse du serveur de tokens, le token ne suit que s'il est accorde
+const TOKEN_REFUSED: u8 = 0;
+const TOKEN_GRANTED: u8 = 1;
+
+// Le client a ce delai pour utiliser son token
+const TOKEN_EXPIRE_SECONDS: u64 = 30;
+// Meme delai que le netcode en mode non securise
+const TOKEN_TIMEOUT_SECONDS: i32 = 5;
+// Un client qui ne repond pas ne doit pas bloquer les suivants
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn random_private_key() -> PrivateKey {
+    generate_random_bytes()
+}
+
+/// Lit une cle ecrite en hexadecimal (64 caracteres)
+pub fn parse_private_key(hex: &str) -> Result<PrivateKey, String> {
+    let error = || format!("cle privee invalide, {} octets en hexadecimal attendus", NETCODE_KEY_BYTES);
+    if hex.len() != NETCODE_KEY_BYTES * 2 || !hex.is_ascii() {
+        return Err(error());
+    }
+    let mut key = [0; NETCODE_KEY_BYTES];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| error())?;
+    }
+    Ok(key)
+}
+
+pub fn parse_password(password: &str) -> Result<MatchPassword, String> {
+    if password.is_empty() || password.len() > PASSWORD_BYTES {
+        return Err(format!(
+            "mot de passe invalide, entre 1 et {} octets attendus",
+            PASSWORD_BYTES
+        ));
+    }
+    let mut bytes = [0; PASSWORD_BYTES];
+    bytes[..password.len()].copy_from_slice(password.as_bytes());
+    Ok(bytes)
+}
+
+// Comparaison en temps constant, la duree ne dit pas combien d'octets sont justes
+fn same_password(a: &MatchPassword, b: &MatchPassword) -> bool {
+    a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Petit serveur TCP qui donne un `ConnectToken` aux clients qui connaissent le mot de passe.
+/// C'est lui qui choisit l'id du client, un joueur ne peut donc plus se faire passer pour un autre.
+/// Le client envoie son `user_data` (le pseudo, recopie tel quel dans le token) puis le mot de passe.
+///
+/// ATTENTION : remplacant pour le developpement et les parties entre amis, pas un vrai service
+/// d'authentification. Tout passe en clair sur TCP, sans TLS : le mot de passe et les cles du
+/// token (client vers serveur et serveur vers client) peuvent etre lus par n'importe qui sur le
+/// chemin. Les requetes sont traitees une par une. A remplacer par un service HTTPS avant
+/// d'exposer un serveur sur internet.
+pub struct TokenServer {
+    listener: TcpListener,
+    private_key: PrivateKey,
+    password: MatchPassword,
+    // Adresses du serveur de jeu ecrites dans les tokens
+    server_addresses: Vec<SocketAddr>,
+    // Arrete avec le serveur de jeu heberge par le client, pour liberer le port
+    shutdown: ShutdownSignal,
+}
+
+impl TokenServer {
+    pub fn bind(
+        addr: SocketAddr,
+        private_key: PrivateKey,
+        password: MatchPassword,
+        server_addresses: Vec<SocketAddr>,
+    ) -> io::Result<Self> {
+        Ok(TokenServer {
+            listener: TcpListener::bind(addr)?,
+            private_key,
+            password,
+            server_addresses,
+            shutdown: ShutdownSignal::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            // `ListenServer::shutdown` se connecte juste pour nous reveiller
+            if self.shutdown.is_requested() {
+                break;
+            }
+            let result = stream.and_then(|mut stream| {
+                stream.set_read_timeout(Some(TOKEN_REQUEST_TIMEOUT))?;
+                stream.set_write_timeout(Some(TOKEN_REQUEST_TIMEOUT))?;
+                let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+                stream.read_exact(&mut user_data)?;
+                let mut password = [0; PASSWORD_BYTES];
+                stream.read_exact(&mut password)?;
+                if !same_password(&password, &self.password) {
+                    stream.write_all(&[TOKEN_REFUSED])?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        format!("wrong password from {}", stream.peer_addr()?),
+                    ));
+                }
+                let token = self.generate_token(stream.local_addr()?, &user_data)?;
+                stream.write_all(&[TOKEN_GRANTED])?;
+                token.write(&mut stream)
+            });
+            if let Err(error) = result {
+                println!("Token server: {}", error);
+            }
+        }
+    }
+
+    fn generate_token(
+        &self,
+        requested_addr: SocketAddr,
+        user_data: &UserData,
+    ) -> io::Result<ConnectToken> {
+        let current_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        // Le client essaie les adresses dans l'ordre : celle qu'il a utilisee pour nous joindre en premier
+        let mut server_addresses = self.server_addresses.clone();
+        server_addresses.sort_by_key(|address| address.ip() != requested_addr.ip());
+        let client_id = rand::thread_rng().gen();
+        ConnectToken::generate(
+            current_time,
+            PROTOCOL_ID,
+            TOKEN_EXPIRE_SECONDS,
+            client_id,
+            TOKEN_TIMEOUT_SECONDS,
+            server_addresses,
+            Some(user_data),
+            &self.private_key,
+        )
+        .map_err(io::Error::other)
+    }
+}
+
+/// Lance le serveur de tokens dans son propre thread
+pub fn spawn_token_server(
+    addr: SocketAddr,
+    private_key: PrivateKey,
+    password: MatchPassword,
+    server_addresses: Vec<SocketAddr>,
+    shutdown: ShutdownSignal,
+) -> io::Result<()> {
+    let mut token_server = TokenServer::bind(addr, private_key, password, server_addresses)?;
+    token_server.shutdown = shutdown;
+    println!("Token server listening on {}", token_server.local_addr()?);
+    std::thread::spawn(move || token_server.run());
+    Ok(())
+}
+
+/// Demande un token au serveur de tokens (bloquant, au plus quelques secondes)
+pub fn request_connect_token(
+    token_server_addr: SocketAddr,
+    user_data: &UserData,
+    password: &MatchPassword,
+) -> io::Result<ConnectToken> {
+    let mut stream = TcpStream::connect_timeout(&token_server_addr, TOKEN_REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(TOKEN_REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(TOKEN_REQUEST_TIMEOUT))?;
+    stream.write_all(user_data)?;
+    stream.write_all(password)?;
+    let mut answer = [TOKEN_REFUSED];
+    stream.read_exact(&mut answer)?;
+    if answer[0] != TOKEN_GRANTED {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "wrong match password",
+        ));
+    }
+    ConnectToken::read(&mut stream).map_err(io::Error::other)
+}
@@ -0,0 +1,20 @@
+use game_test::connection::{ConnectionSettings, USAGE};
+use game_test::server;
+
+fn main() {
+    let settings = match ConnectionSettings::from_args(std::env::args().skip(1)) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(1);
+        }
+    };
+    // Jamais arrete : le processus sert uniquement ce serveur
+    let server_transport = server::new_renet_server(&settings, &server::ShutdownSignal::default())
+        .expect("Failed to start the server");
+    println!(
+        "Dedicated server listening on {:?}",
+        server_transport.addresses()
+    );
+    server::new_server_app(server_transport, settings.tick_rate, settings.teams).run();
+}
@@ -0,0 +1,42 @@
+use game_test::auth::TokenServer;
+use game_test::connection::{ConnectionSettings, USAGE};
+
+/// Serveur de tokens autonome, pour un serveur de jeu lance avec `--secure --key <hex> --token-server <host:port>`.
+/// Ecoute sur `--token-server` et signe des tokens pour le serveur de jeu decrit par `--port`, `--public`, ...
+fn main() {
+    let settings = match ConnectionSettings::from_args(std::env::args().skip(1)) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(1);
+        }
+    };
+    let Some(private_key) = settings.private_key else {
+        eprintln!("--key est obligatoire\n{}", USAGE);
+        std::process::exit(1);
+    };
+    let bind_addr = settings
+        .resolve_token_server_addr(settings.server_bind_addr())
+        .expect("Invalid token server address");
+    let password = match settings.require_password() {
+        Ok(password) => password,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(1);
+        }
+    };
+    let token_server = TokenServer::bind(
+        bind_addr,
+        private_key,
+        password,
+        settings.server_public_addrs(),
+    )
+    .expect("Failed to bind the token server socket");
+    println!("Dev-only token server: passwords and tokens travel in clear, do not expose it");
+    println!(
+        "Token server listening on {} for {:?}",
+        token_server.local_addr().unwrap(),
+        settings.server_public_addrs()
+    );
+    token_server.run();
+}
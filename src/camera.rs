@@ -4,6 +4,7 @@ use bevy::render::camera::{ScalingMode, Viewport};
 use bevy::render::view::RenderLayers;
 use bevy_rapier3d::prelude::*;
 
+use crate::health::{Armor, Health};
 use crate::player::*;
 use crate::weapon::spawn_weapon;
 
@@ -12,7 +13,10 @@ use crate::weapon::spawn_weapon;
 const VIEW_MODEL_RENDER_LAYER: usize = 1;
 
 #[derive(Debug, Component)]
-struct WorldModelCamera;
+pub struct WorldModelCamera;
+
+// Vitesse a laquelle la camera rattrape une correction du serveur
+const CORRECTION_SMOOTHING: f32 = 10.0;
 
 pub fn spawn_crosshair(mut commands: Commands) {
     commands
@@ -30,7 +34,7 @@ pub fn spawn_crosshair(mut commands: Commands) {
         ))
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
-                concat!("+",),
+                "+",
                 TextStyle {
                     font_size: 25.0,
                     ..default()
@@ -61,6 +65,25 @@ pub fn move_camera(
     }
 }
 
+/// Decale la camera de la vue pour absorber la correction de position du serveur,
+/// le joueur est deja a la bonne position mais on ne le voit pas sauter
+pub fn smooth_prediction_correction(
+    time: Res<Time>,
+    mut player: Query<(&Transform, &mut PredictionCorrection), With<Player>>,
+    mut camera: Query<&mut Transform, (With<WorldModelCamera>, Without<Player>)>,
+) {
+    let Ok((player_transform, mut correction)) = player.get_single_mut() else {
+        return;
+    };
+    correction.offset *= (-CORRECTION_SMOOTHING * time.delta_seconds()).exp();
+    if correction.offset.length_squared() < 1e-6 {
+        correction.offset = Vec3::ZERO;
+    }
+    if let Ok(mut camera_transform) = camera.get_single_mut() {
+        camera_transform.translation = player_transform.rotation.inverse() * correction.offset;
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Minimap;
 
@@ -83,7 +106,7 @@ pub fn spawn_view_model(
             camera_3d: Camera3d { ..default() },
             ..default()
         },
-        Minimap::default(),
+        Minimap,
         Name::new("MinimapCamera"),
     );
 
@@ -94,25 +117,14 @@ pub fn spawn_view_model(
                 transform: Transform::from_xyz(0.0, 1.3, 0.0),
                 ..default()
             },
-            Collider::round_cylinder(0.9, 0.3, 0.2),
-            KinematicCharacterController {
-                custom_mass: Some(5.0),
-                up: Vec3::Y,
-                offset: CharacterLength::Absolute(0.01),
-                slide: true,
-                autostep: Some(CharacterAutostep {
-                    max_height: CharacterLength::Relative(0.3),
-                    min_width: CharacterLength::Relative(0.5),
-                    include_dynamic_bodies: false,
-                }),
-                // Don’t allow climbing slopes larger than 45 degrees.
-                max_slope_climb_angle: 45.0_f32.to_radians(),
-                // Automatically slide down on slopes smaller than 30 degrees.
-                min_slope_slide_angle: 30.0_f32.to_radians(),
-                apply_impulse_to_dynamic_bodies: true,
-                snap_to_ground: None,
-                ..default()
-            },
+            player_collider(),
+            player_controller(),
+            MovementState::default(),
+            InputHistory::default(),
+            PredictionCorrection::default(),
+            Health::default(),
+            Armor::default(),
+            Affiliation::default(),
         ))
         .insert(ActiveEvents::COLLISION_EVENTS)
         // .insert(GravityScale(0.))
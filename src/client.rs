@@ -1,85 +1,313 @@
 use bevy::{
     app::{App, Plugin, Update},
     asset::Assets,
-    color::{Color, LinearRgba},
-    math::Vec3,
+    color::Color,
+    math::{Quat, Vec3},
     pbr::{NotShadowCaster, PbrBundle, StandardMaterial},
     prelude::{
-        in_state, Capsule3d, Commands, Entity, IntoSystemConfigs, Mesh, ResMut, Resource, Sphere, Transform
+        in_state, resource_exists, BuildChildren, Capsule3d, Commands, Component, DespawnRecursiveExt, Entity, EventWriter, IntoSystemConfigs, Mesh, Query, Res, ResMut, Resource, Transform, Visibility, With, Without
     },
-    time::{Timer, TimerMode},
+    time::{Time, Timer, TimerMode},
 };
-use bevy_rapier3d::prelude::{ActiveEvents, Collider, RigidBody};
+use bevy_rapier3d::prelude::{ActiveEvents, Collider, ColliderDisabled, RigidBody};
 use renet::{
     transport::{ClientAuthentication, NetcodeClientTransport},
-    ClientId, ConnectionConfig, DefaultChannel, RenetClient,
+    ClientId, RenetClient,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::SystemTime,
 };
-use std::{collections::HashMap, net::UdpSocket, time::SystemTime};
 
-use crate::server::{Lobby, ServerMessages};
+use crate::ammo::LocalAmmo;
+use crate::hitscan;
+use crate::inventory::Inventory;
+use crate::auth;
+use crate::username;
+use crate::connection::{ConnectionSettings, PROTOCOL_ID};
+use crate::protocol::{self, Channel, DecodeErrors};
+use crate::server::ServerMessages;
+use crate::kill_feed::KillFeedEvent;
+use crate::name_tag;
+use crate::scoreboard::Scoreboard;
+use crate::weapon_definition::WeaponRegistry;
+use crate::snapshot::{self, SnapshotHistory, WorldState};
 use crate::{
-    projectile::{Lifetime, Projectile, ProjectilePosition},
+    health::{Armor, Health},
+    player::{
+        self, Affiliation, InputHistory, MovementState, Player, PlayerInput, PlayerState, PredictionCorrection,
+        RespawnCountdown,
+    },
+    projectile::{self, Projectile},
     test,
+    weapon::WeaponId,
 };
 
+/// Messages envoyes par un client. Ils ne portent pas d'id : le serveur utilise
+/// celui de la connexion sur laquelle ils arrivent.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ClientMessages {
+    // Canal non fiable : chaque paquet repete les commandes pas encore confirmees,
+    // `sequence` est celle de la derniere de `inputs` (rangees de la plus ancienne a la plus recente)
+    PlayerInput { sequence: u32, inputs: Vec<PlayerInput> },
+    // `view_time` : heure serveur des joueurs distants affiches au moment du tir,
+    // le serveur remet les cibles a cet instant pour verifier la touche
+    // `shot` : numero du tir chez ce client, renvoye avec l'id donne par le serveur
+    ProjectileSpawned { shot: u32, weapon: WeaponId, position: Vec3, direction: Vec3, view_time: f64 },
+    TestMessage { message: String },
+    // Dernier WorldSnapshot recu, le serveur envoie les suivants en delta par rapport a lui
+    SnapshotAck { tick: u32 },
+    // Debut d'un rechargement, le serveur bloque les tirs de cette arme pendant `reload_time`
+    Reload { weapon: WeaponId },
+    // Arme sortie par le joueur, les tirs suivants doivent venir d'elle
+    SwitchWeapon { weapon: WeaponId },
+    // Tir instantane tel que le tireur l'a vu : `victim` est le joueur qu'il dit avoir touche,
+    // verifie par le serveur. `distance` ne sert qu'a la trainee chez les autres joueurs.
+    HitscanFired {
+        weapon: WeaponId,
+        origin: Vec3,
+        direction: Vec3,
+        distance: f32,
+        view_time: f64,
+        victim: Option<ClientId>,
+    },
+}
+
+impl ClientMessages {
+    pub fn channel(&self) -> Channel {
+        match self {
+            ClientMessages::TestMessage { .. } => Channel::Lobby,
+            // Un changement d'arme ou un rechargement ne doit pas doubler le tir d'avant
+            ClientMessages::ProjectileSpawned { .. }
+            | ClientMessages::Reload { .. }
+            | ClientMessages::SwitchWeapon { .. }
+            | ClientMessages::HitscanFired { .. } => Channel::Actions,
+            // Seul le plus recent compte. Une commande perdue revient avec le paquet suivant,
+            // sans bloquer les autres derriere elle comme sur un canal fiable
+            ClientMessages::PlayerInput { .. } | ClientMessages::SnapshotAck { .. } => {
+                Channel::Movement
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default, Resource)]
 pub struct Entities {
     pub players: HashMap<ClientId, Entity>,
+    // Pseudos retenus par le serveur, le notre compris
+    pub names: HashMap<ClientId, String>,
     // pub projectiles: HashMap<ClientId, Entity>,
 }
 
+impl Entities {
+    pub fn name(&self, id: ClientId) -> String {
+        self.names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| player::placeholder_name(id))
+    }
+}
+
+/// Positions et orientations recues du serveur pour un joueur distant, avec l'heure serveur du tick
+#[derive(Debug, Default, Component)]
+pub struct SnapshotBuffer {
+    pub snapshots: VecDeque<(f64, Vec3, Quat)>,
+}
+
+/// Horloge du serveur vue par le client, pour dater les snapshots avec leur numero de tick
+#[derive(Debug, Default, Resource)]
+pub struct ServerClock {
+    // Connue a la reception de ServerInfo, les snapshots sont ignores avant
+    pub tick_rate: Option<u32>,
+    pub latest_tick: u32,
+    // Heure serveur estimee moins heure locale
+    offset: Option<f64>,
+}
+
+impl ServerClock {
+    pub fn server_time(&self, local_time: f64) -> f64 {
+        local_time + self.offset.unwrap_or(0.0)
+    }
+
+    /// Heure serveur a laquelle les joueurs distants sont affiches
+    pub fn render_time(&self, local_time: f64) -> f64 {
+        self.server_time(local_time) - INTERPOLATION_DELAY
+    }
+
+    /// Heure serveur du tick, ou `None` si le snapshot arrive apres un plus recent
+    pub fn receive_tick(&mut self, tick: u32, local_time: f64) -> Option<f64> {
+        let tick_rate = self.tick_rate?;
+        if tick <= self.latest_tick {
+            return None;
+        }
+        self.latest_tick = tick;
+        let tick_time = tick as f64 / tick_rate as f64;
+        let sample = tick_time - local_time;
+        // Un paquet en avance recale tout de suite l'horloge, un paquet en retard la fait juste deriver
+        self.offset = Some(match self.offset {
+            Some(offset) if sample < offset => offset + (sample - offset) * CLOCK_DRIFT,
+            _ => sample,
+        });
+        Some(tick_time)
+    }
+}
+
 pub struct Client;
 
 impl Plugin for Client {
     fn build(&self, app: &mut App) {
-        let (client, client_transport) = new_renet_client();
-        app.insert_resource(client);
-        app.insert_resource(client_transport);
+        // La connexion est creee quand on clique sur "Join" ou "Create"
         app.init_resource::<Entities>();
+        app.init_resource::<DecodeErrors>();
+        app.init_resource::<ServerClock>();
+        app.init_resource::<SnapshotHistory>();
+        app.init_resource::<Scoreboard>();
+        app.init_resource::<LocalAmmo>();
+        app.init_resource::<Inventory>();
+        app.add_event::<KillFeedEvent>();
         app.add_systems(
             Update,
-            client_sync_players.run_if(in_state(test::GameState::Game)),
+            (client_sync_players, interpolate_remote_players)
+                .chain()
+                .run_if(in_state(test::GameState::Game))
+                .run_if(resource_exists::<RenetClient>),
         );
     }
 }
 
-const PROTOCOL_ID: u64 = 7;
+// Les joueurs distants sont affiches avec ce retard pour toujours avoir deux positions a interpoler
+const INTERPOLATION_DELAY: f64 = 0.1;
+// Quand les paquets n'arrivent plus, on prolonge le dernier mouvement au plus pendant ce temps
+const MAX_EXTRAPOLATION: f64 = 0.1;
+const MAX_SNAPSHOTS: usize = 32;
+const CLOCK_DRIFT: f64 = 0.05;
+/// Commandes repetees au plus par paquet : un quart de seconde a 60 Hz
+pub const MAX_INPUTS_PER_MESSAGE: usize = 16;
 
-pub fn new_renet_client() -> (RenetClient, NetcodeClientTransport) {
-    let server_addr = (local_ip_address::local_ip().unwrap().to_string() + ":5000").parse().unwrap();
-    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+pub fn new_renet_client(
+    settings: &ConnectionSettings,
+    server_addr: SocketAddr,
+) -> io::Result<(RenetClient, NetcodeClientTransport)> {
+    let socket = UdpSocket::bind(ConnectionSettings::client_bind_addr(&server_addr))?;
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
-    let client_id = current_time.as_millis() as u64;
-    let authentication = ClientAuthentication::Unsecure {
-        client_id,
-        protocol_id: PROTOCOL_ID,
-        server_addr,
-        user_data: None,
+    // Le pseudo voyage avec la connexion, le serveur le valide et renvoie le nom retenu
+    let user_data = username::to_user_data(&settings.username);
+    let authentication = if settings.secure {
+        // L'id du client est choisi par le serveur de tokens
+        let token_server_addr = settings.resolve_token_server_addr(server_addr)?;
+        ClientAuthentication::Secure {
+            connect_token: auth::request_connect_token(
+                token_server_addr,
+                &user_data,
+                &settings.require_password()?,
+            )?,
+        }
+    } else {
+        ClientAuthentication::Unsecure {
+            client_id: current_time.as_millis() as u64,
+            protocol_id: PROTOCOL_ID,
+            server_addr,
+            user_data: Some(user_data),
+        }
     };
 
-    let transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
-    let client = RenetClient::new(ConnectionConfig::default());
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket)
+        .map_err(io::Error::other)?;
+    let client = RenetClient::new(protocol::connection_config());
+
+    Ok((client, transport))
+}
+
+/// Remplace la connexion en cours par une nouvelle vers `server_addr`
+pub fn connect_to_server(
+    commands: &mut Commands,
+    settings: &ConnectionSettings,
+    server_addr: SocketAddr,
+) -> io::Result<()> {
+    println!("Connecting to {}", server_addr);
+    let (client, client_transport) = new_renet_client(settings, server_addr)?;
+    commands.insert_resource(ServerClock::default());
+    commands.insert_resource(SnapshotHistory::default());
+    commands.insert_resource(Scoreboard::default());
+    commands.insert_resource(LocalAmmo::default());
+    commands.insert_resource(Inventory::default());
+    commands.insert_resource(client);
+    commands.insert_resource(client_transport);
+    Ok(())
+}
 
-    (client, transport)
+/// Abandonne la connexion en cours (tentative annulee ou echouee)
+pub fn disconnect_from_server(commands: &mut Commands) {
+    commands.remove_resource::<RenetClient>();
+    commands.remove_resource::<NetcodeClientTransport>();
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn client_sync_players(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut client: ResMut<RenetClient>,
-    mut lobby: ResMut<Lobby>,
     transport: ResMut<NetcodeClientTransport>,
     mut entities: ResMut<Entities>,
+    mut local_player: Query<
+        (
+            &mut Transform,
+            &mut MovementState,
+            &mut InputHistory,
+            &mut PredictionCorrection,
+            &mut Affiliation,
+            &mut Health,
+            &mut Armor,
+        ),
+        With<Player>,
+    >,
+    mut remote_players: Query<
+        (&mut Transform, &mut SnapshotBuffer, &mut Health, &mut Visibility),
+        Without<Player>,
+    >,
+    time: Res<Time>,
+    mut decode_errors: ResMut<DecodeErrors>,
+    mut clock: ResMut<ServerClock>,
+    mut snapshots: ResMut<SnapshotHistory>,
+    mut player_state: ResMut<PlayerState>,
+    mut projectiles: Query<(Entity, &mut Projectile)>,
+    // Interface : tableau des scores et kill feed
+    (mut scoreboard, mut kill_feed): (ResMut<Scoreboard>, EventWriter<KillFeedEvent>),
+    // Armes et munitions du joueur local
+    (weapons, mut local_ammo): (Res<WeaponRegistry>, ResMut<LocalAmmo>),
 ) {
-    while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        let server_message = bincode::deserialize(&message).unwrap();
+    let mut snapshot_ack = None;
+    while let Some(message) = Channel::ALL
+        .into_iter()
+        .find_map(|channel| client.receive_message(channel))
+    {
+        let server_message = match protocol::decode(&message) {
+            Ok(server_message) => server_message,
+            Err(error) => {
+                decode_errors.count += 1;
+                println!("Client side : ignoring message from server: {}", error);
+                continue;
+            }
+        };
         match server_message {
-            ServerMessages::PlayerConnected { id, position } => {
+            ServerMessages::PlayerConnected { id, name, position, affiliation } => {
                 // println!("Client side : hashmap player: {:?}", entities.players);
-                if id != transport.client_id() {
+                entities.names.insert(id, name);
+                if id == transport.client_id() {
+                    // Point de depart et equipe choisis par le serveur
+                    if let Ok((mut transform, _, _, _, mut local_affiliation, ..)) =
+                        local_player.get_single_mut()
+                    {
+                        transform.translation = position;
+                        *local_affiliation = affiliation;
+                    }
+                } else if let Entry::Vacant(entry) = entities.players.entry(id) {
                     let player_entity = commands
                         .spawn(PbrBundle {
                             mesh: meshes.add(Capsule3d::new(0.3, 1.8)),
@@ -91,138 +319,282 @@ pub fn client_sync_players(
                             ..Default::default()
                         })
                         .insert(Collider::round_cylinder(0.9, 0.3, 0.2))
-                        .insert(RigidBody::Dynamic)
+                        // Deplace uniquement par l'interpolation, la physique locale ne peut pas le pousser
+                        .insert(RigidBody::KinematicPositionBased)
                         .insert(NotShadowCaster)
                         .insert(ActiveEvents::COLLISION_EVENTS)
+                        // Reste a sa position de depart jusqu'au premier WorldSnapshot
+                        .insert(SnapshotBuffer::default())
+                        .insert(Health::default())
+                        .insert(affiliation)
+                        .with_children(|parent| {
+                            parent.spawn(name_tag::outline(&mut meshes, &mut materials));
+                        })
                         .id();
 
                     // Ajouter l'entité à la hashmap
-                    entities.players.insert(id, player_entity);
-                    let message =
-                        bincode::serialize(&ServerMessages::PlayerConnected { id, position })
-                            .unwrap();
-                    client.send_message(DefaultChannel::ReliableOrdered, message);
+                    entry.insert(player_entity);
                 }
             }
             ServerMessages::PlayerDisconnected { id } => {
                 println!("Client side : Player {} disconnected.", id);
-                lobby.players.remove(&id);
+                entities.names.remove(&id);
+                if let Some(player_entity) = entities.players.remove(&id) {
+                    // Avec son contour
+                    commands.entity(player_entity).despawn_recursive();
+                }
             }
-            ServerMessages::PlayerMoved { id, position } => {
-                if let Some(player_entity) = entities.players.get(&id) {
-                    commands
-                        .entity(*player_entity)
-                        .insert(Transform::from_translation(position));
-                    // println!("Client side : Player {} moved to {:?}", id, position);
+            ServerMessages::ServerInfo { tick_rate } => {
+                clock.tick_rate = Some(tick_rate);
+            }
+            ServerMessages::WorldSnapshot {
+                tick,
+                baseline,
+                ack,
+                removed,
+                players,
+            } => {
+                let empty = WorldState::new();
+                let baseline_state = if baseline == 0 {
+                    Some(&empty)
+                } else {
+                    snapshots.get(baseline)
+                };
+                // Reference deja oubliee : le serveur repartira d'un snapshot plus recent qu'on a confirme
+                let Some(baseline_state) = baseline_state else {
+                    continue;
+                };
+                let state = snapshot::apply(baseline_state, &removed, &players);
+                // Canal non fiable : un snapshot plus vieux que le dernier recu est jete
+                let Some(tick_time) = clock.receive_tick(tick, time.elapsed_seconds_f64()) else {
+                    continue;
+                };
+                // La reconciliation est faite par player::reconcile_player au prochain tick
+                if let Ok((_, _, mut history, ..)) = local_player.get_single_mut() {
+                    history.receive_ack(ack);
                 }
+                for (id, player) in state.iter() {
+                    let Some(player_entity) = entities.players.get(id) else {
+                        continue;
+                    };
+                    if let Ok((_, mut buffer, ..)) = remote_players.get_mut(*player_entity) {
+                        buffer.snapshots.push_back((
+                            tick_time,
+                            player.position(),
+                            Quat::from_rotation_y(player.yaw()),
+                        ));
+                        if buffer.snapshots.len() > MAX_SNAPSHOTS {
+                            buffer.snapshots.pop_front();
+                        }
+                    }
+                }
+                snapshots.push(tick, state);
+                snapshot_ack = Some(tick);
             }
             ServerMessages::ProjectileSpawned {
-                id,
+                projectile_id,
+                owner,
+                shot,
+                weapon,
                 position,
                 direction,
             } => {
                 // println!("Projectile spawned from server id: {:?} et position: {:?}, client receiver id : {}", id, position, transport.client_id());
-                if id != transport.client_id() {
-                    commands
-                        .spawn(PbrBundle {
-                            mesh: meshes.add(Sphere::new(0.01)),
-                            material: materials.add(StandardMaterial {
-                                base_color: Color::srgb(1., 1., 0.),
-                                emissive: LinearRgba::rgb(10.0, 10., 0.),
-                                ..Default::default()
-                            }),
-                            transform: Transform::from_translation(position),
-                            ..Default::default()
-                        })
-                        .insert(Projectile)
-                        .insert(ProjectilePosition {
-                            direction,
-                            speed: 70.0, // Vitesse du projectile
-                            // speed: 0.5,
-                            projectile_id: SystemTime::now()
-                                .duration_since(SystemTime::UNIX_EPOCH)
-                                .unwrap()
-                                .as_millis() as u64,
-                        })
-                        .insert(Lifetime {
-                            timer: Timer::from_seconds(10.0, TimerMode::Once), // Durée de vie du projectile
-                        })
-                        .insert(Collider::ball(0.01))
-                        .insert(RigidBody::Dynamic)
-                        .insert(NotShadowCaster)
-                        .insert(ActiveEvents::COLLISION_EVENTS);
-
-                    // Ajouter l'entité à la hashmap
-                    // entities.projectiles.insert(id, projectile_entity);
+                if owner == transport.client_id() {
+                    // Notre projectile existe deja, il recoit juste son id
+                    if let Some((_, mut projectile)) = projectiles
+                        .iter_mut()
+                        .find(|(_, projectile)| projectile.owner == owner && projectile.shot == shot)
+                    {
+                        projectile.network_id = Some(projectile_id);
+                    }
+                } else if let Some(definition) = weapons.get(weapon) {
+                    let projectile = Projectile {
+                        owner,
+                        weapon,
+                        shot,
+                        network_id: Some(projectile_id),
+                    };
+                    commands.spawn(projectile::projectile_bundle(
+                        &mut meshes,
+                        &mut materials,
+                        projectile,
+                        position,
+                        direction,
+                        definition,
+                    ));
                 }
             }
             ServerMessages::TestMessage { message } => {
                 println!("Client side : Message test from server : {}", message);
             }
-            ServerMessages::PlayerDeath { id } => {
-                if id != transport.client_id() {
-                    if let Some(player_entity) = entities.players.get(&id) {
-                        commands.entity(*player_entity).despawn();
-                        entities.players.remove(&id);
+            ServerMessages::PlayerHit {
+                projectile_id,
+                shooter,
+                victim,
+                ..
+            } => {
+                if shooter == transport.client_id() {
+                    println!("Client side : hit player {}", victim);
+                }
+                // La balle s'arrete dans la cible
+                if let Some((entity, _)) = projectiles
+                    .iter()
+                    .find(|(_, projectile)| projectile.network_id == Some(projectile_id))
+                {
+                    commands.entity(entity).despawn();
+                }
+            }
+            ServerMessages::HealthChanged { id, health, armor } => {
+                if id == transport.client_id() {
+                    if let Ok((.., mut local_health, mut local_armor)) = local_player.get_single_mut()
+                    {
+                        *local_health = health;
+                        *local_armor = armor;
+                    }
+                } else if let Some((entity, (_, _, mut remote_health, mut visibility))) = entities
+                    .players
+                    .get(&id)
+                    .and_then(|entity| Some((*entity, remote_players.get_mut(*entity).ok()?)))
+                {
+                    *remote_health = health;
+                    // Un mort disparait jusqu'a son respawn, sans arreter les tirs ni les joueurs
+                    if health.is_dead() {
+                        *visibility = Visibility::Hidden;
+                        commands.entity(entity).insert(ColliderDisabled);
+                    } else {
+                        *visibility = Visibility::Inherited;
+                        commands.entity(entity).remove::<ColliderDisabled>();
+                    }
+                }
+            }
+            ServerMessages::PlayerDeath {
+                killer,
+                victim,
+                weapon,
+                headshot,
+                respawn_delay,
+            } => {
+                kill_feed.send(KillFeedEvent {
+                    killer,
+                    victim,
+                    weapon,
+                    headshot,
+                });
+                if victim == transport.client_id() {
+                    *player_state = PlayerState::Dead;
+                    commands.insert_resource(RespawnCountdown {
+                        timer: Timer::from_seconds(respawn_delay, TimerMode::Once),
+                    });
+                }
+            }
+            ServerMessages::PlayerRespawned { id, position } => {
+                if id == transport.client_id() {
+                    if let Ok((mut transform, mut movement, _, mut correction, ..)) =
+                        local_player.get_single_mut()
+                    {
+                        transform.translation = position;
+                        *movement = MovementState::default();
+                        correction.offset = Vec3::ZERO;
                     }
+                    *player_state = PlayerState::Alive;
+                    commands.remove_resource::<RespawnCountdown>();
+                    local_ammo.reset();
+                } else if let Some((mut transform, mut buffer, ..)) = entities
+                    .players
+                    .get(&id)
+                    .and_then(|entity| remote_players.get_mut(*entity).ok())
+                {
+                    // Pas d'interpolation entre l'endroit de la mort et le point de depart
+                    buffer.snapshots.clear();
+                    transform.translation = position;
                 }
             }
+            ServerMessages::Scoreboard { players } => {
+                scoreboard.entries = players;
+            }
+            ServerMessages::AmmoChanged { weapon, ammo } => {
+                local_ammo.set(weapon, ammo);
+            }
+            ServerMessages::HitscanFired { origin, end, .. } => {
+                hitscan::spawn_tracer(&mut commands, &mut meshes, &mut materials, origin, end);
+            }
         }
     }
+
+    // Un seul accuse par frame, pour le snapshot le plus recent
+    if let Some(tick) = snapshot_ack {
+        let message = ClientMessages::SnapshotAck { tick };
+        client.send_message(message.channel(), protocol::encode(&message));
+    }
 }
 
-pub fn client_send_input(
-    client_position: Vec3,
-    mut client: ResMut<RenetClient>,
-    transport: ResMut<NetcodeClientTransport>,
+/// Place les joueurs distants `INTERPOLATION_DELAY` dans le passe (en heure serveur),
+/// entre les deux snapshots qui l'encadrent
+pub fn interpolate_remote_players(
+    time: Res<Time>,
+    clock: Res<ServerClock>,
+    mut remote_players: Query<(&mut Transform, &mut SnapshotBuffer)>,
 ) {
-    let input_message = bincode::serialize(&ServerMessages::PlayerMoved {
-        id: transport.client_id(),
-        position: client_position,
-    })
-    .unwrap();
+    let render_time = clock.render_time(time.elapsed_seconds_f64());
+    for (mut transform, mut buffer) in remote_players.iter_mut() {
+        // On garde une seule position plus ancienne que render_time
+        while buffer.snapshots.len() > 2 && buffer.snapshots[1].0 <= render_time {
+            buffer.snapshots.pop_front();
+        }
+        let Some(&(last_time, last_position, last_rotation)) = buffer.snapshots.back() else {
+            continue;
+        };
+        let (first_time, first_position, first_rotation) = buffer.snapshots[0];
 
-    client.send_message(DefaultChannel::ReliableOrdered, input_message);
+        (transform.translation, transform.rotation) = if render_time <= first_time {
+            (first_position, first_rotation)
+        } else if render_time < last_time {
+            let (to_time, to_position, to_rotation) = buffer.snapshots[1];
+            let t = ((render_time - first_time) / (to_time - first_time)) as f32;
+            (
+                first_position.lerp(to_position, t),
+                first_rotation.slerp(to_rotation, t),
+            )
+        } else if buffer.snapshots.len() >= 2 && last_time > first_time {
+            // Plus de position recente : on prolonge la derniere vitesse connue, puis on s'arrete
+            let velocity = (last_position - first_position) / (last_time - first_time) as f32;
+            let ahead = (render_time - last_time).min(MAX_EXTRAPOLATION);
+            (last_position + velocity * ahead as f32, last_rotation)
+        } else {
+            (last_position, last_rotation)
+        };
+    }
 }
 
-pub fn client_send_position(
-    client_position: Vec3,
-    mut client: ResMut<RenetClient>,
-    transport: ResMut<NetcodeClientTransport>,
-) {
-    // println!("le joueur (clients) 1 {:?}", client_position);
-    let input_message = bincode::serialize(&ServerMessages::PlayerMoved {
-        id: transport.client_id(),
-        position: client_position,
-    })
-    .unwrap();
-    client.send_message(DefaultChannel::ReliableOrdered, input_message);
+pub fn client_send_input(history: &InputHistory, mut client: ResMut<RenetClient>) {
+    let records = history.to_send(MAX_INPUTS_PER_MESSAGE);
+    let Some(last) = records.last() else {
+        return;
+    };
+    let input_message = ClientMessages::PlayerInput {
+        sequence: last.sequence,
+        inputs: records.iter().map(|record| record.input).collect(),
+    };
+
+    client.send_message(input_message.channel(), protocol::encode(&input_message));
 }
 
 pub fn client_send_projectile_position(
+    shot: u32,
+    weapon: WeaponId,
     projectile_position: Vec3,
     client: &mut RenetClient,
-    transport: &NetcodeClientTransport,
     direction: Vec3,
+    view_time: f64,
 ) {
     // println!("Projectile position: {:?}", projectile_position);
-    let input_message = bincode::serialize(&ServerMessages::ProjectileSpawned {
-        id: transport.client_id(),
+    let input_message = ClientMessages::ProjectileSpawned {
+        shot,
+        weapon,
         position: projectile_position,
         direction,
-    })
-    .unwrap();
-    client.send_message(DefaultChannel::ReliableOrdered, input_message);
+        view_time,
+    };
+    client.send_message(input_message.channel(), protocol::encode(&input_message));
 }
-
-// pub fn client_send_he_is_dead(
-//     mut client: ResMut<RenetClient>,
-//     transport: ResMut<NetcodeClientTransport>,
-// ) {
-//     // println!("le joueur (clients) 1 {:?}", client_position);
-//     let input_message = bincode::serialize(&ServerMessages::PlayerDeath {
-//         id: transport.client_id(),
-//     })
-//     .unwrap();
-//     client.send_message(DefaultChannel::ReliableOrdered, input_message);
-// }
@@ -0,0 +1,259 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+use bevy::prelude::Resource;
+
+use crate::auth::{self, MatchPassword, PrivateKey};
+
+pub const DEFAULT_PORT: u16 = 5000;
+pub const PROTOCOL_ID: u64 = 7;
+pub const DEFAULT_TICK_RATE: u32 = 60;
+pub const MAX_TEAMS: u8 = 8;
+
+pub const USAGE: &str = "Options:
+    --bind <ip>           adresse sur laquelle le serveur ecoute (defaut 0.0.0.0 ou ::)
+    --public <ip>         adresse publique annoncee par le serveur (defaut : ip locale)
+    --port <port>         port du serveur (defaut 5000)
+    --ipv6                utilise IPv6 au lieu d'IPv4
+    --tick-rate <hz>      frequence des ticks du serveur, entre 10 et 60 (defaut 60)
+    --teams <n>           partie par equipes, entre 2 et 8 (defaut : chacun pour soi)
+    --connect <host:port> serveur a rejoindre (defaut : ip locale)
+    --secure              connexion avec un token signe par le serveur de tokens
+                          (developpement seulement : mot de passe et cles passent en clair, sans TLS)
+    --password <secret>   mot de passe de la partie privee, obligatoire avec --secure
+    --key <hex>           cle privee partagee avec le serveur de tokens (64 caracteres hexa)
+    --token-server <host:port>
+                          serveur de tokens (defaut : integre au serveur de jeu, meme port en TCP)";
+
+/// Adresses utilisees pour heberger et pour rejoindre une partie.
+/// Remplie depuis la ligne de commande puis modifiable depuis le menu "Start".
+#[derive(Debug, Clone, Resource)]
+pub struct ConnectionSettings {
+    // Adresse locale sur laquelle le serveur ecoute, `None` pour toutes les interfaces
+    pub bind_address: Option<IpAddr>,
+    // Adresse que les clients utilisent pour joindre ce serveur (box derriere un NAT, ...)
+    pub public_address: Option<IpAddr>,
+    pub port: u16,
+    pub ipv6: bool,
+    // Nombre de WorldSnapshot envoyes par seconde par le serveur
+    pub tick_rate: u32,
+    // Nombre d'equipes de la partie hebergee, 0 pour chacun pour soi
+    pub teams: u8,
+    // Serveur a rejoindre, sous la forme host:port
+    pub server_address: String,
+    pub secure: bool,
+    // Exige par le serveur de tokens avant de donner un token
+    pub password: Option<MatchPassword>,
+    // Sans cle, le serveur en tire une au hasard pour son serveur de tokens integre
+    pub private_key: Option<PrivateKey>,
+    pub token_server_address: Option<String>,
+    // Pseudo tape au lancement du client
+    pub username: String,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        let host = local_ip_address::local_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| Ipv4Addr::LOCALHOST.to_string());
+        ConnectionSettings {
+            bind_address: None,
+            public_address: None,
+            port: DEFAULT_PORT,
+            ipv6: false,
+            tick_rate: DEFAULT_TICK_RATE,
+            teams: 0,
+            server_address: format!("{}:{}", host, DEFAULT_PORT),
+            secure: false,
+            password: None,
+            private_key: None,
+            token_server_address: None,
+            username: String::from("Player"),
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut settings = ConnectionSettings::default();
+        let mut server_address = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} attend une valeur", arg));
+            match arg.as_str() {
+                "--bind" => settings.bind_address = Some(parse_ip(&value()?)?),
+                "--public" => settings.public_address = Some(parse_ip(&value()?)?),
+                "--port" => {
+                    let port = value()?;
+                    settings.port = port.parse().map_err(|_| format!("port invalide : {}", port))?;
+                }
+                "--ipv6" => settings.ipv6 = true,
+                "--tick-rate" => {
+                    let tick_rate = value()?;
+                    settings.tick_rate = tick_rate
+                        .parse()
+                        .ok()
+                        .filter(|tick_rate| (10..=60).contains(tick_rate))
+                        .ok_or(format!("frequence invalide : {}", tick_rate))?;
+                }
+                "--teams" => {
+                    let teams = value()?;
+                    settings.teams = teams
+                        .parse()
+                        .ok()
+                        .filter(|teams| (2..=MAX_TEAMS).contains(teams))
+                        .ok_or(format!("nombre d'equipes invalide : {}", teams))?;
+                }
+                "--connect" => server_address = Some(value()?),
+                "--secure" => settings.secure = true,
+                "--password" => settings.password = Some(auth::parse_password(&value()?)?),
+                "--key" => settings.private_key = Some(auth::parse_private_key(&value()?)?),
+                "--token-server" => settings.token_server_address = Some(value()?),
+                _ => return Err(format!("option inconnue : {}", arg)),
+            }
+        }
+
+        settings.server_address = match server_address {
+            Some(address) => address,
+            None => format!("{}:{}", settings.default_host(), settings.port),
+        };
+        Ok(settings)
+    }
+
+    /// Adresses de la partie hebergee, tapees dans le menu "Start". Un champ vide garde le defaut.
+    /// Le serveur a rejoindre (`server_address`) n'est pas touche.
+    pub fn set_host_addresses(
+        &mut self,
+        bind: &str,
+        public: &str,
+        port: &str,
+    ) -> Result<(), String> {
+        let optional_ip = |value: &str| match value.trim() {
+            "" => Ok(None),
+            value => parse_ip(value).map(Some),
+        };
+        let bind_address = optional_ip(bind)?;
+        let public_address = optional_ip(public)?;
+        let port = match port.trim() {
+            "" => DEFAULT_PORT,
+            port => port
+                .parse()
+                .map_err(|_| format!("port invalide : {}", port))?,
+        };
+        self.bind_address = bind_address;
+        self.public_address = public_address;
+        self.port = port;
+        Ok(())
+    }
+
+    pub fn toggle_ipv6(&mut self) {
+        self.ipv6 = !self.ipv6;
+    }
+
+    fn unspecified(&self) -> IpAddr {
+        if self.ipv6 {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }
+    }
+
+    fn loopback(&self) -> IpAddr {
+        if self.ipv6 {
+            IpAddr::V6(Ipv6Addr::LOCALHOST)
+        } else {
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        }
+    }
+
+    fn default_host(&self) -> String {
+        let local_ip = if self.ipv6 {
+            local_ip_address::local_ipv6()
+        } else {
+            local_ip_address::local_ip()
+        };
+        match local_ip.unwrap_or_else(|_| self.loopback()) {
+            IpAddr::V6(ip) => format!("[{}]", ip),
+            ip => ip.to_string(),
+        }
+    }
+
+    pub fn server_bind_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address.unwrap_or_else(|| self.unspecified()), self.port)
+    }
+
+    /// Adresses acceptees par le serveur : netcode refuse un client qui vise une adresse absente de la liste
+    pub fn server_public_addrs(&self) -> Vec<SocketAddr> {
+        let local_ip = if self.ipv6 {
+            local_ip_address::local_ipv6()
+        } else {
+            local_ip_address::local_ip()
+        };
+        let mut addresses = Vec::new();
+        for ip in [self.public_address, self.bind_address, local_ip.ok(), Some(self.loopback())]
+            .into_iter()
+            .flatten()
+        {
+            let address = SocketAddr::new(ip, self.port);
+            if !ip.is_unspecified() && !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        addresses
+    }
+
+    /// Adresse pour rejoindre le serveur heberge par ce client
+    pub fn local_server_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.loopback(), self.port)
+    }
+
+    pub fn resolve_server_addr(&self) -> io::Result<SocketAddr> {
+        self.resolve(&self.server_address)
+    }
+
+    /// Serveur de tokens a joindre en TCP : par defaut le serveur de jeu lui-meme
+    pub fn resolve_token_server_addr(&self, server_addr: SocketAddr) -> io::Result<SocketAddr> {
+        match &self.token_server_address {
+            Some(address) => self.resolve(address),
+            None => Ok(server_addr),
+        }
+    }
+
+    /// Mot de passe de la partie, obligatoire en mode securise
+    pub fn require_password(&self) -> io::Result<MatchPassword> {
+        self.password.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "--secure demande aussi --password")
+        })
+    }
+
+    fn resolve(&self, address: &str) -> io::Result<SocketAddr> {
+        let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+        addresses
+            .iter()
+            .find(|address| address.is_ipv6() == self.ipv6)
+            .or(addresses.first())
+            .copied()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("adresse introuvable : {}", address),
+                )
+            })
+    }
+
+    /// Adresse locale du client, de la meme famille que le serveur vise
+    pub fn client_bind_addr(server_addr: &SocketAddr) -> SocketAddr {
+        if server_addr.is_ipv6() {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0)
+        } else {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)
+        }
+    }
+}
+
+fn parse_ip(value: &str) -> Result<IpAddr, String> {
+    value
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse()
+        .map_err(|_| format!("adresse ip invalide : {}", value))
+}
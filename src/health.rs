@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use renet::ClientId;
+use serde::{Deserialize, Serialize};
+
+use crate::player::Player;
+use crate::weapon::WeaponId;
+
+pub const MAX_HEALTH: f32 = 100.0;
+// Armure donnee a chaque joueur a son arrivee, il n'y a pas encore de bonus a ramasser
+pub const STARTING_ARMOR: f32 = 25.0;
+// Part des degats prise par l'armure tant qu'il lui reste des points
+pub const ARMOR_ABSORPTION: f32 = 0.5;
+
+// Hauteur de l'impact par rapport au centre du collider (demi-hauteur totale 1.1)
+const HEAD_HEIGHT: f32 = 0.6;
+const LEGS_HEIGHT: f32 = -0.3;
+
+/// Points de vie d'un joueur. Le serveur les modifie, les clients recoivent `HealthChanged`.
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            current: MAX_HEALTH,
+            max: MAX_HEALTH,
+        }
+    }
+}
+
+impl Health {
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Armure : absorbe `absorption` des degats jusqu'a ce que ses points tombent a zero
+#[derive(Debug, Clone, Copy, PartialEq, Component, Serialize, Deserialize)]
+pub struct Armor {
+    pub points: f32,
+    pub absorption: f32,
+}
+
+impl Default for Armor {
+    fn default() -> Self {
+        Armor {
+            points: STARTING_ARMOR,
+            absorption: ARMOR_ABSORPTION,
+        }
+    }
+}
+
+/// Partie du corps touchee, elle multiplie les degats de l'arme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HitLocation {
+    Head,
+    Body,
+    Legs,
+}
+
+impl HitLocation {
+    /// `height` : hauteur de l'impact moins celle du centre du joueur
+    pub fn from_height(height: f32) -> Self {
+        if height > HEAD_HEIGHT {
+            HitLocation::Head
+        } else if height < LEGS_HEIGHT {
+            HitLocation::Legs
+        } else {
+            HitLocation::Body
+        }
+    }
+
+    pub fn multiplier(self) -> f32 {
+        match self {
+            HitLocation::Head => 2.0,
+            HitLocation::Body => 1.0,
+            HitLocation::Legs => 0.75,
+        }
+    }
+}
+
+/// Degats confirmes par le serveur, appliques par `server_apply_damage`
+#[derive(Debug, Clone, Copy, Event)]
+pub struct DamageEvent {
+    pub attacker: ClientId,
+    pub victim: ClientId,
+    pub weapon: WeaponId,
+    pub location: HitLocation,
+    // Degats avant l'armure, multiplicateur de `location` deja compte
+    pub amount: f32,
+}
+
+/// Passe d'abord par l'armure puis par la vie. Renvoie les points de vie perdus.
+pub fn apply_damage(health: &mut Health, armor: &mut Armor, amount: f32) -> f32 {
+    let absorbed = (amount * armor.absorption).min(armor.points).max(0.0);
+    armor.points -= absorbed;
+    let taken = (amount - absorbed).min(health.current).max(0.0);
+    health.current -= taken;
+    taken
+}
+
+#[derive(Debug, Component)]
+pub struct HealthText;
+
+pub fn spawn_health_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            left: Val::Px(20.0),
+            ..default()
+        }),
+        HealthText,
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_health_hud(
+    player: Query<(&Health, &Armor), (With<Player>, Or<(Changed<Health>, Changed<Armor>)>)>,
+    mut text: Query<&mut Text, With<HealthText>>,
+) {
+    let (Ok((health, armor)), Ok(mut text)) = (player.get_single(), text.get_single_mut()) else {
+        return;
+    };
+    text.sections[0].value = format!(
+        "{:.0} HP   {:.0} armure",
+        health.current.ceil(),
+        armor.points.ceil()
+    );
+}
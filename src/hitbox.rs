@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::{
+    math::{Quat, Vec3},
+    prelude::Resource,
+};
+use renet::ClientId;
+
+use crate::player::player_collider;
+
+/// Ticks gardes pour rembobiner les joueurs, 1 s a 60 Hz
+pub const HITBOX_HISTORY: usize = 64;
+/// On ne remonte jamais plus loin : un tireur avec un gros ping doit viser un peu devant
+pub const MAX_REWIND_SECONDS: f64 = 0.3;
+
+/// Positions des joueurs a chaque tick
+pub type Hitboxes = BTreeMap<ClientId, Vec3>;
+
+/// Positions des joueurs des derniers ticks, pour rejouer un tir dans le monde que le tireur voyait
+#[derive(Debug, Default, Resource)]
+pub struct HitboxHistory {
+    ticks: VecDeque<(u32, Hitboxes)>,
+}
+
+impl HitboxHistory {
+    pub fn push(&mut self, tick: u32, hitboxes: Hitboxes) {
+        if self.ticks.len() == HITBOX_HISTORY {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back((tick, hitboxes));
+    }
+
+    /// Joueurs a l'instant `tick` (fractionnaire), interpoles comme le client les affiche.
+    /// Un instant hors de l'historique est ramene au tick le plus proche.
+    pub fn rewind(&self, tick: f64) -> Hitboxes {
+        let Some(after) = self.ticks.iter().position(|(t, _)| *t as f64 >= tick) else {
+            return self.ticks.back().map(|(_, hitboxes)| hitboxes.clone()).unwrap_or_default();
+        };
+        let (to_tick, to) = &self.ticks[after];
+        if after == 0 {
+            return to.clone();
+        }
+        let (from_tick, from) = &self.ticks[after - 1];
+        let t = ((tick - *from_tick as f64) / (*to_tick - *from_tick) as f64) as f32;
+        // Un joueur arrive ou parti entre les deux ticks garde sa seule position connue
+        from.iter()
+            .map(|(id, position)| (*id, to.get(id).map_or(*position, |to| position.lerp(*to, t))))
+            .collect()
+    }
+}
+
+/// Premier joueur touche par le rayon, avec sa distance, sans compter le tireur.
+/// Le collider est symetrique autour de Y : seule la position compte.
+pub fn ray_test(
+    hitboxes: &Hitboxes,
+    shooter: ClientId,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<(ClientId, f32)> {
+    let collider = player_collider();
+    hitboxes
+        .iter()
+        .filter(|(id, _)| **id != shooter)
+        .filter_map(|(id, position)| {
+            collider
+                .cast_ray(*position, Quat::IDENTITY, origin, direction, max_distance, true)
+                .map(|distance| (*id, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Tir instantane : le joueur que le tireur dit avoir touche doit etre le premier sur le rayon
+/// rembobine. Renvoie la distance du coup si c'est le cas.
+pub fn confirm_claim(
+    hitboxes: &Hitboxes,
+    shooter: ClientId,
+    claim: ClientId,
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+) -> Option<f32> {
+    ray_test(hitboxes, shooter, origin, direction, max_distance)
+        .filter(|(victim, _)| *victim == claim)
+        .map(|(_, distance)| distance)
+}
+
+/// Projectile simule par le serveur dans le temps du tireur : `tick` part de l'instant que le
+/// tireur voyait au moment du tir et avance d'un tick a chaque tick du serveur. Pendant tout le vol
+/// les joueurs sont rembobines a cet instant, comme chez le tireur qui les voit croiser sa balle.
+#[derive(Debug, Clone, Copy)]
+pub struct FlyingProjectile {
+    pub shooter: ClientId,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub tick: f64,
+    // Temps de vol restant, en secondes
+    pub remaining: f32,
+}
+
+/// Resultat d'un tick de vol
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flight {
+    Flying,
+    // `height` : hauteur de l'impact par rapport au centre de la cible
+    Hit {
+        victim: ClientId,
+        position: Vec3,
+        height: f32,
+    },
+    // Arrete par un mur ou en fin de vie
+    Stopped,
+}
+
+impl FlyingProjectile {
+    /// Direction et longueur du trajet du prochain tick
+    pub fn segment(&self, dt: f32) -> (Vec3, f32) {
+        let step = self.velocity * dt;
+        (step.normalize_or_zero(), step.length())
+    }
+
+    /// Avance d'un tick de `dt` secondes. `wall` est la distance du premier mur sur le segment,
+    /// un joueur plus proche que lui est touche.
+    pub fn advance(&mut self, history: &HitboxHistory, dt: f32, wall: Option<f32>) -> Flight {
+        let (direction, length) = self.segment(dt);
+        if direction == Vec3::ZERO {
+            return Flight::Stopped;
+        }
+        let hitboxes = history.rewind(self.tick);
+        let reach = wall.map_or(length, |wall| wall.min(length));
+        if let Some((victim, distance)) =
+            ray_test(&hitboxes, self.shooter, self.position, direction, reach)
+        {
+            let position = self.position + direction * distance;
+            return Flight::Hit {
+                victim,
+                position,
+                height: position.y - hitboxes[&victim].y,
+            };
+        }
+        if wall.is_some_and(|wall| wall <= length) {
+            return Flight::Stopped;
+        }
+        self.position += direction * length;
+        self.tick += 1.0;
+        self.remaining -= dt;
+        if self.remaining <= 0.0 {
+            Flight::Stopped
+        } else {
+            Flight::Flying
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::{QueryFilter, RapierContext};
+use renet::{ClientId, RenetClient};
+
+use crate::camera::WorldModelCamera;
+use crate::client::{ClientMessages, Entities};
+use crate::player::Player;
+use crate::projectile::{apply_spread, muzzle};
+use crate::protocol;
+use crate::server::MAX_SHOT_DISTANCE;
+use crate::weapon::Weapon;
+use crate::weapon_definition::WeaponDefinition;
+
+const TRACER_SECONDS: f32 = 0.1;
+const TRACER_WIDTH: f32 = 0.02;
+
+/// Trainee d'un tir instantane, qui s'amincit avant de disparaitre
+#[derive(Debug, Component)]
+pub struct Tracer {
+    timer: Timer,
+}
+
+pub fn spawn_tracer(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    start: Vec3,
+    end: Vec3,
+) {
+    let length = start.distance(end);
+    if !length.is_finite() || length <= 0.0 {
+        return;
+    }
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(TRACER_WIDTH, TRACER_WIDTH, length)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::srgb(1., 1., 0.),
+                emissive: LinearRgba::rgb(10.0, 10., 0.),
+                ..Default::default()
+            }),
+            transform: Transform::from_translation(start.lerp(end, 0.5)).looking_at(end, Vec3::Y),
+            ..Default::default()
+        },
+        Tracer {
+            timer: Timer::from_seconds(TRACER_SECONDS, TimerMode::Once),
+        },
+        NotShadowCaster,
+    ));
+}
+
+pub fn update_tracers(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut tracers: Query<(Entity, &mut Tracer, &mut Transform)>,
+) {
+    for (entity, mut tracer, mut transform) in tracers.iter_mut() {
+        if tracer.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let width = 1.0 - tracer.timer.fraction();
+        transform.scale = Vec3::new(width, width, 1.0);
+    }
+}
+
+/// Premier mur ou joueur sur le rayon, avec sa distance. Le tireur ne l'arrete pas,
+/// les projectiles n'ont pas de collider.
+pub fn cast_shot(
+    rapier_context: &RapierContext,
+    entities: &Entities,
+    shooter: Entity,
+    origin: Vec3,
+    direction: Vec3,
+) -> (f32, Option<ClientId>) {
+    let filter = QueryFilter::default()
+        .exclude_sensors()
+        .exclude_collider(shooter);
+    let Some((entity, distance)) =
+        rapier_context.cast_ray(origin, direction, MAX_SHOT_DISTANCE, true, filter)
+    else {
+        return (MAX_SHOT_DISTANCE, None);
+    };
+    let victim = entities
+        .players
+        .iter()
+        .find(|(_, player)| **player == entity)
+        .map(|(id, _)| *id);
+    (distance, victim)
+}
+
+/// Tir depuis la camera du joueur : la trainee part du canon, la touche est annoncee au serveur
+#[allow(clippy::too_many_arguments)]
+pub fn fire_hitscan(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    client: &mut RenetClient,
+    rapier_context: &RapierContext,
+    entities: &Entities,
+    camera: &Query<&GlobalTransform, With<WorldModelCamera>>,
+    weapon_query: &Query<(&Transform, &Parent), With<Weapon>>,
+    player_query: &Query<&Transform, With<Player>>,
+    // Heure serveur des joueurs distants affiches, le serveur rembobine au meme instant
+    view_time: f64,
+    definition: &WeaponDefinition,
+) {
+    let (Ok(camera), Ok((weapon_transform, parent))) =
+        (camera.get_single(), weapon_query.get_single())
+    else {
+        return;
+    };
+    // L'arme est un enfant du joueur
+    let player_entity = parent.get();
+    let Ok(player_transform) = player_query.get(player_entity) else {
+        return;
+    };
+    let origin = camera.translation();
+    let direction = apply_spread(camera.forward().as_vec3(), definition.spread).normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return;
+    }
+    let (distance, victim) = cast_shot(rapier_context, entities, player_entity, origin, direction);
+
+    spawn_tracer(
+        commands,
+        meshes,
+        materials,
+        muzzle(weapon_transform, player_transform),
+        origin + direction * distance,
+    );
+    let message = ClientMessages::HitscanFired {
+        weapon: definition.id,
+        origin,
+        direction,
+        distance,
+        view_time,
+        victim,
+    };
+    client.send_message(message.channel(), protocol::encode(&message));
+}
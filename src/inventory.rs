@@ -0,0 +1,142 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use renet::RenetClient;
+
+use crate::ammo::LocalAmmo;
+use crate::client::ClientMessages;
+use crate::keybind::KeyBinds;
+use crate::protocol;
+use crate::weapon::{Weapon, WeaponId, DEFAULT_WEAPON};
+use crate::weapon_definition::WeaponRegistry;
+
+/// Armes portees a l'apparition, une par emplacement
+pub const LOADOUT: [WeaponId; 2] = [DEFAULT_WEAPON, 1];
+
+/// Emplacements d'armes du joueur local et arme en main
+#[derive(Debug, Resource)]
+pub struct Inventory {
+    pub slots: Vec<WeaponId>,
+    pub current: usize,
+    // Arme en train d'etre sortie : pas de tir avant la fin du timer
+    pub switch: Option<Timer>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory {
+            slots: LOADOUT.to_vec(),
+            current: 0,
+            switch: None,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn weapon(&self) -> WeaponId {
+        self.slots
+            .get(self.current)
+            .copied()
+            .unwrap_or(DEFAULT_WEAPON)
+    }
+
+    pub fn is_switching(&self) -> bool {
+        self.switch.is_some()
+    }
+
+    /// Avancement du changement d'arme entre 0 et 1, pour l'animation du bras
+    pub fn switch_progress(&self) -> Option<f32> {
+        self.switch.as_ref().map(Timer::fraction)
+    }
+
+    /// Emplacement a `offset` crans de l'arme en main, en bouclant
+    pub fn cycle(&self, offset: i32) -> usize {
+        (self.current as i32 + offset).rem_euclid(self.slots.len().max(1) as i32) as usize
+    }
+
+    /// Sort l'arme de l'emplacement `slot`, `false` s'il n'existe pas ou s'il est deja en main
+    pub fn select(&mut self, slot: usize, equip_time: f32) -> bool {
+        if slot >= self.slots.len() || slot == self.current {
+            return false;
+        }
+        self.current = slot;
+        self.switch = Some(Timer::from_seconds(equip_time, TimerMode::Once));
+        true
+    }
+}
+
+/// Touches numerotees et molette. Le serveur refuse les tirs tant que l'arme n'est pas sortie.
+#[allow(clippy::too_many_arguments)]
+pub fn switch_weapon(
+    time: Res<Time>,
+    key_binds: Res<KeyBinds>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    weapons: Res<WeaponRegistry>,
+    mut inventory: ResMut<Inventory>,
+    mut local_ammo: ResMut<LocalAmmo>,
+    mut client: ResMut<RenetClient>,
+) {
+    if let Some(timer) = inventory.switch.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            inventory.switch = None;
+        }
+    }
+
+    // Molette vers le haut : arme precedente
+    let scroll: f32 = wheel.read().map(|event| event.y).sum();
+    let slot = key_binds
+        .weapon_slots
+        .iter()
+        .position(|key| key_input.just_pressed(*key))
+        .or_else(|| (scroll != 0.0).then(|| inventory.cycle(-scroll.signum() as i32)));
+    let Some(slot) = slot else {
+        return;
+    };
+    // Une arme pas encore chargee ne peut pas etre sortie
+    let Some(definition) = inventory
+        .slots
+        .get(slot)
+        .and_then(|weapon| weapons.get(*weapon))
+    else {
+        return;
+    };
+    if !inventory.select(slot, definition.equip_time) {
+        return;
+    }
+    // Ranger l'arme annule son rechargement, le serveur fait pareil
+    local_ammo.reload = None;
+    let message = ClientMessages::SwitchWeapon {
+        weapon: definition.id,
+    };
+    client.send_message(message.channel(), protocol::encode(&message));
+}
+
+/// Remplace le pave du bras par celui de l'arme en main
+#[allow(clippy::type_complexity)]
+pub fn update_view_model(
+    weapons: Res<WeaponRegistry>,
+    inventory: Res<Inventory>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut arm: Query<(Entity, &mut Handle<Mesh>, &mut Handle<StandardMaterial>), With<Weapon>>,
+    // Bras et arme deja affichee, le bras est recree a chaque partie
+    mut shown: Local<Option<(Entity, WeaponId)>>,
+) {
+    let weapon = inventory.weapon();
+    let (Some(definition), Ok((entity, mut mesh, mut material))) =
+        (weapons.get(weapon), arm.get_single_mut())
+    else {
+        return;
+    };
+    if *shown == Some((entity, weapon)) {
+        return;
+    }
+    let (x, y, z) = definition.view_model.size;
+    let (r, g, b) = definition.view_model.color;
+    *mesh = meshes.add(Cuboid::new(x, y, z));
+    *material = materials.add(StandardMaterial {
+        base_color: Color::srgb(r, g, b),
+        ..Default::default()
+    });
+    *shown = Some((entity, weapon));
+}
@@ -14,6 +14,11 @@ pub struct KeyBinds {
 
     pub shoot: MouseButton,
     pub aim: MouseButton,
+    pub reload: KeyCode,
+    // Un emplacement d'arme par touche, dans l'ordre de l'inventaire
+    pub weapon_slots: [KeyCode; 9],
+
+    pub scoreboard: KeyCode,
 }
 
 impl Default for KeyBinds {
@@ -27,22 +32,30 @@ impl Default for KeyBinds {
             jump: KeyCode::Space,
             shoot: MouseButton::Left,
             aim: MouseButton::Right,
+            reload: KeyCode::KeyR,
+            weapon_slots: [
+                KeyCode::Digit1,
+                KeyCode::Digit2,
+                KeyCode::Digit3,
+                KeyCode::Digit4,
+                KeyCode::Digit5,
+                KeyCode::Digit6,
+                KeyCode::Digit7,
+                KeyCode::Digit8,
+                KeyCode::Digit9,
+            ],
+            scoreboard: KeyCode::Tab,
         }
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, States)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, States)]
 pub enum KeybindingState {
+    #[default]
     Normal,
     Rebinding(String),
 }
 
-impl Default for KeybindingState {
-    fn default() -> Self {
-        KeybindingState::Normal
-    }
-}
-
 fn _key_rebinding_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut key_bindings: ResMut<KeyBinds>,
@@ -59,6 +72,7 @@ fn _key_rebinding_system(
                 "move_right" => key_bindings.move_right = *key,
                 "sprint" => key_bindings.sprint = *key,
                 "jump" => key_bindings.jump = *key,
+                "reload" => key_bindings.reload = *key,
                 _ => (),
             }
             next_state.set(KeybindingState::Normal);
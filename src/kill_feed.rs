@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use renet::{transport::NetcodeClientTransport, ClientId};
+
+use crate::client::Entities;
+use crate::weapon::WeaponId;
+use crate::weapon_definition::WeaponRegistry;
+
+/// Duree d'affichage d'une ligne, fondu compris
+pub const KILL_FEED_SECONDS: f32 = 5.0;
+const FADE_SECONDS: f32 = 1.0;
+const MAX_KILL_FEED_ROWS: usize = 5;
+const ROW_BACKGROUND_ALPHA: f32 = 0.4;
+
+/// Mort annoncee par le serveur, affichee dans le coin de l'ecran
+#[derive(Debug, Clone, Copy, Event)]
+pub struct KillFeedEvent {
+    pub killer: ClientId,
+    pub victim: ClientId,
+    pub weapon: WeaponId,
+    pub headshot: bool,
+}
+
+/// Opacite d'une ligne affichee depuis `age` secondes : pleine puis fondu vers 0
+pub fn alpha(age: f32) -> f32 {
+    ((KILL_FEED_SECONDS - age) / FADE_SECONDS).clamp(0.0, 1.0)
+}
+
+#[derive(Debug, Component)]
+pub struct KillFeed;
+
+#[derive(Debug, Component)]
+pub struct KillFeedRow {
+    pub age: f32,
+}
+
+// Pastille headshot / suicide, son fond suit le fondu de la ligne
+#[derive(Debug, Component)]
+pub struct KillFeedIcon;
+
+pub fn spawn_kill_feed(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.0),
+                right: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            },
+            ..default()
+        },
+        KillFeed,
+    ));
+}
+
+fn text(value: String, color: Color) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 22.0,
+            color,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        margin: UiRect::horizontal(Val::Px(4.0)),
+        ..default()
+    })
+}
+
+fn icon(value: &str, background: Color) -> (TextBundle, KillFeedIcon) {
+    (
+        text(value.to_string(), Color::WHITE).with_background_color(background),
+        KillFeedIcon,
+    )
+}
+
+/// Ajoute les nouvelles morts en bas de la liste et fait disparaitre les plus anciennes
+#[allow(clippy::too_many_arguments)]
+pub fn update_kill_feed(
+    mut commands: Commands,
+    time: Res<Time>,
+    entities: Res<Entities>,
+    weapons: Res<WeaponRegistry>,
+    transport: Option<Res<NetcodeClientTransport>>,
+    mut events: EventReader<KillFeedEvent>,
+    feed: Query<Entity, With<KillFeed>>,
+    mut rows: Query<(Entity, &mut KillFeedRow, &mut BackgroundColor, &Children)>,
+    mut texts: Query<(&mut Text, &mut BackgroundColor, Has<KillFeedIcon>), Without<KillFeedRow>>,
+) {
+    let Ok(feed) = feed.get_single() else {
+        events.clear();
+        return;
+    };
+    let own_id = transport.map(|transport| transport.client_id());
+    let name_color = |id: ClientId| {
+        if Some(id) == own_id {
+            Color::srgb(1.0, 0.8, 0.2)
+        } else {
+            Color::WHITE
+        }
+    };
+
+    let events: Vec<KillFeedEvent> = events.read().copied().collect();
+    let mut ages: Vec<(f32, Entity)> = rows
+        .iter()
+        .map(|(entity, row, ..)| (row.age, entity))
+        .collect();
+    ages.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let excess = (ages.len() + events.len()).saturating_sub(MAX_KILL_FEED_ROWS);
+    let dropped: Vec<Entity> = ages
+        .into_iter()
+        .take(excess)
+        .map(|(_, entity)| entity)
+        .collect();
+    for entity in &dropped {
+        commands.entity(*entity).despawn_recursive();
+    }
+
+    for event in events {
+        let row = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        margin: UiRect::vertical(Val::Px(2.0)),
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::srgba(
+                        0.0,
+                        0.0,
+                        0.0,
+                        ROW_BACKGROUND_ALPHA,
+                    )),
+                    ..default()
+                },
+                KillFeedRow { age: 0.0 },
+            ))
+            .with_children(|row| {
+                // Un suicide n'affiche que la victime
+                if event.killer != event.victim {
+                    row.spawn(text(entities.name(event.killer), name_color(event.killer)));
+                }
+                row.spawn(text(
+                    format!(
+                        "[{}]",
+                        weapons
+                            .get(event.weapon)
+                            .map_or("?", |definition| definition.name.as_str())
+                    ),
+                    Color::srgb(0.7, 0.7, 0.7),
+                ));
+                if event.headshot {
+                    row.spawn(icon("HS", Color::srgb(0.8, 0.1, 0.1)));
+                }
+                if event.killer == event.victim {
+                    row.spawn(icon("SUICIDE", Color::srgb(0.4, 0.1, 0.6)));
+                }
+                row.spawn(text(entities.name(event.victim), name_color(event.victim)));
+            })
+            .id();
+        commands.entity(feed).add_child(row);
+    }
+
+    for (entity, mut row, mut background, children) in rows.iter_mut() {
+        row.age += time.delta_seconds();
+        if dropped.contains(&entity) {
+            continue;
+        }
+        if row.age >= KILL_FEED_SECONDS {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = alpha(row.age);
+        background.0.set_alpha(ROW_BACKGROUND_ALPHA * alpha);
+        for child in children {
+            if let Ok((mut text, mut background, is_icon)) = texts.get_mut(*child) {
+                for section in text.sections.iter_mut() {
+                    section.style.color.set_alpha(alpha);
+                }
+                if is_icon {
+                    background.0.set_alpha(alpha);
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,26 @@
+pub mod ammo;
+pub mod inventory;
+pub mod auth;
 pub mod camera;
+pub mod connection;
 pub mod map;
 // pub mod menu;
 pub mod player;
 pub mod projectile;
+pub mod protocol;
 pub mod test;
 pub mod weapon;
+pub mod weapon_definition;
 pub mod keybind;
 pub mod settings;
+pub mod snapshot;
+pub mod health;
+pub mod hitbox;
+pub mod hitscan;
+pub mod kill_feed;
+pub mod name_tag;
+pub mod scoreboard;
+pub mod username;
 pub mod client;
 pub mod server;
 
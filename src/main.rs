@@ -12,12 +12,20 @@ use game_test::camera;
 use game_test::keybind::KeyBinds;
 use game_test::{map, test};
 use bevy_renet::*;
-use game_test::{server, client};
-use game_test::{player, projectile, weapon};
+use game_test::client;
+use game_test::connection::{ConnectionSettings, USAGE};
+use game_test::{ammo, health, hitscan, inventory, kill_feed, name_tag, player, projectile, scoreboard, weapon, weapon_definition};
 use transport::NetcodeClientPlugin;
-use transport::NetcodeServerPlugin;
 
 fn main() {
+    let mut connection_settings = match ConnectionSettings::from_args(std::env::args().skip(1)) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            std::process::exit(1);
+        }
+    };
+
     print!("Saisissez votre Username: ");
     io::stdout().flush().unwrap(); // Assurez-vous que le message est affiché avant de lire l'entrée
 
@@ -25,10 +33,15 @@ fn main() {
     io::stdin().read_line(&mut username).expect("Failed to read line");
 
     // Supprimer le caractère de nouvelle ligne à la fin de la chaîne
-    // let username = username.trim();
+    let username = username.trim();
+    if !username.is_empty() {
+        connection_settings.username = username.to_string();
+    }
     
-    let mut cursor = Cursor::default();
-    cursor.visible = true;
+    let cursor = Cursor {
+        visible: true,
+        ..default()
+    };
     // let (server, server_transport) = server::new_renet_server();
     
     App::new()
@@ -38,10 +51,14 @@ fn main() {
         .init_resource::<player::MovementInput>()
         .insert_resource(KeyBinds::default())
         .init_resource::<player::LookInput>()
+        .init_resource::<test::HostState>()
+        .init_resource::<test::JoinStatus>()
+        .init_resource::<test::HostFields>()
+        .insert_resource(connection_settings)
+        // Les commandes du joueur sont envoyees et predites a ce rythme
+        .insert_resource(Time::<Fixed>::from_hz(player::INPUT_HZ))
         // PLUGINS ###############################################
-        .add_plugins(server::Server)
-        .add_plugins(RenetServerPlugin)
-        .add_plugins(NetcodeServerPlugin)
+        // Le serveur tourne dans son propre thread quand on clique sur "Create"
         .add_plugins(client::Client)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -52,6 +69,8 @@ fn main() {
             }),
             ..default()
         }))
+        // Apres DefaultPlugins : les armes sont des assets
+        .add_plugins(weapon_definition::WeaponDefinitions)
         .add_plugins((
             FpsCounterPlugin,
             RapierPhysicsPlugin::<NoUserData>::default(),
@@ -68,7 +87,17 @@ fn main() {
         )
         .add_systems(
             Update,
-            test::button_system.run_if(in_state(test::GameState::Menu)),
+            (
+                test::button_system,
+                test::server_address_input,
+                test::host_address_input,
+                test::update_join_status,
+                test::update_connection_texts,
+                test::update_join_texts,
+                test::update_host_field_texts,
+            )
+                .chain()
+                .run_if(in_state(test::GameState::Menu)),
         )
         .add_systems(
             OnExit(test::GameState::Menu),
@@ -82,6 +111,10 @@ fn main() {
                 map::spawn_world_model,
                 map::spawn_lights,
                 camera::spawn_crosshair,
+                health::spawn_health_hud,
+                scoreboard::spawn_scoreboard,
+                kill_feed::spawn_kill_feed,
+                ammo::spawn_ammo_hud,
             ),
         )
         .add_systems(
@@ -95,17 +128,35 @@ fn main() {
             (
                 player::handle_input.run_if(in_state(test::GameState::Game)),
                 camera::move_camera.run_if(in_state(test::GameState::Game)),
+                inventory::switch_weapon
+                    .before(ammo::update_reload)
+                    .run_if(in_state(test::GameState::Game)),
+                inventory::update_view_model.run_if(in_state(test::GameState::Game)),
+                ammo::update_reload
+                    .before(weapon::pew)
+                    .run_if(in_state(test::GameState::Game)),
                 weapon::pew.run_if(in_state(test::GameState::Game)),
                 projectile::detect_collisions.run_if(in_state(test::GameState::Game)),
+                hitscan::update_tracers.run_if(in_state(test::GameState::Game)),
                 player::la_mooooooooooort.run_if(in_state(test::GameState::Game)),
+                health::update_health_hud.run_if(in_state(test::GameState::Game)),
+                scoreboard::update_scoreboard.run_if(in_state(test::GameState::Game)),
+                kill_feed::update_kill_feed.run_if(in_state(test::GameState::Game)),
+                ammo::update_ammo_hud.run_if(in_state(test::GameState::Game)),
+                name_tag::update_name_tags
+                    .after(client::interpolate_remote_players)
+                    .run_if(in_state(test::GameState::Game)),
+                name_tag::update_outlines.run_if(in_state(test::GameState::Game)),
             )
                 .run_if(client_connected),
         )
         .add_systems(
             FixedUpdate,
             (
-                projectile::update_projectiles.run_if(in_state(test::GameState::Game)),
-                player::player_movement.run_if(in_state(test::GameState::Game)),
+                (player::reconcile_player, player::player_movement)
+                    .chain()
+                    .run_if(in_state(test::GameState::Game))
+                    .run_if(client_connected),
                 weapon::update_arm.run_if(in_state(test::GameState::Game)),
                 camera::move_camera.run_if(in_state(test::GameState::Game)),
             ),
@@ -114,9 +165,9 @@ fn main() {
             PostUpdate,
             (
                 camera::update_minimap.run_if(in_state(test::GameState::Game)),
+                camera::smooth_prediction_correction.run_if(in_state(test::GameState::Game)),
                 camera::update_minimap_camera_rotation.run_if(in_state(test::GameState::Game)),
                 projectile::update_projectiles.run_if(in_state(test::GameState::Game)),
-                player::player_movement.run_if(in_state(test::GameState::Game)),
             ),
         )
         .run();
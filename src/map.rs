@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use bevy::render::view::RenderLayers;
 use bevy_rapier3d::prelude::*;
 
+use rand::Rng;
 use std::fs::File;
 use std::io::Read;
 
@@ -24,6 +25,94 @@ const DEFAULT_RENDER_LAYER: usize = 0;
 /// The light source belongs to both layers.
 const VIEW_MODEL_RENDER_LAYER: usize = 1;
 const TILE_SIZE: f32 = 2.0;
+const MAP_PATH: &str = "assets/maps/map00.txt";
+// Hauteur du centre d'un joueur pose au sol
+const SPAWN_HEIGHT: f32 = 1.3;
+
+/// Points de depart des joueurs, marques par un `S` dans le fichier de map
+#[derive(Debug, Default, Resource)]
+pub struct SpawnPoints {
+    pub points: Vec<Vec3>,
+}
+
+impl SpawnPoints {
+    /// Le point le plus loin du plus proche ennemi en vie, au hasard s'il n'y a personne
+    pub fn choose(&self, enemies: &[Vec3]) -> Vec3 {
+        let fallback = Vec3::new(0.0, SPAWN_HEIGHT, 0.0);
+        if enemies.is_empty() {
+            let index = rand::thread_rng().gen_range(0..self.points.len().max(1));
+            return self.points.get(index).copied().unwrap_or(fallback);
+        }
+        let nearest_enemy = |point: &Vec3| {
+            enemies
+                .iter()
+                .map(|enemy| point.distance_squared(*enemy))
+                .fold(f32::INFINITY, f32::min)
+        };
+        self.points
+            .iter()
+            .copied()
+            .max_by(|a, b| nearest_enemy(a).total_cmp(&nearest_enemy(b)))
+            .unwrap_or(fallback)
+    }
+}
+
+/// Forme d'un morceau de mur de la map, partagee par le rendu client et les colliders du serveur
+#[derive(Debug, Clone, Copy)]
+enum WallShape {
+    Horizontal,
+    Vertical,
+    Diagonal,
+}
+
+impl WallShape {
+    fn collider(&self) -> Collider {
+        match self {
+            WallShape::Horizontal | WallShape::Vertical => Collider::cuboid(0.6, 5., 0.6),
+            WallShape::Diagonal => Collider::cuboid(TILE_SIZE.sqrt(), 5., 0.6),
+        }
+    }
+}
+
+/// Lit le fichier de map et retourne chaque morceau de mur avec sa position, puis les points de depart
+fn load_map(path: &str) -> (Vec<(WallShape, Transform)>, Vec<Vec3>) {
+    let mut file = File::open(path).unwrap();
+
+    let mut content = String::new();
+
+    file.read_to_string(&mut content).unwrap();
+
+    let tilemap = content.split("\n");
+    let mut walls = Vec::new();
+    let mut spawn_points = Vec::new();
+
+    for (y, tile) in tilemap.into_iter().enumerate() {
+        for (x, elem) in tile.chars().enumerate() {
+            let translation = Vec3::new(x as f32 - 64., 0.9, y as f32 - 64.);
+            match elem {
+                '_' => walls.push((WallShape::Horizontal, Transform::from_translation(translation))),
+                '|' => walls.push((WallShape::Vertical, Transform::from_translation(translation))),
+                '/' => walls.push((
+                    WallShape::Diagonal,
+                    Transform::from_translation(translation)
+                        .with_rotation(Quat::from_rotation_y(45_f32.to_radians())),
+                )),
+                '\\' => walls.push((
+                    WallShape::Diagonal,
+                    Transform::from_translation(translation)
+                        .with_rotation(Quat::from_rotation_y(135_f32.to_radians())),
+                )),
+                '0' => {
+                    walls.push((WallShape::Horizontal, Transform::from_translation(translation)));
+                    walls.push((WallShape::Vertical, Transform::from_translation(translation)));
+                }
+                'S' => spawn_points.push(translation.with_y(SPAWN_HEIGHT)),
+                _ => {}
+            }
+        }
+    }
+    (walls, spawn_points)
+}
 
 pub fn spawn_world_model(
     mut commands: Commands,
@@ -48,106 +137,24 @@ pub fn spawn_world_model(
     let material = materials.add(Color::srgb(0.5, 0.2, 0.1));
     let _temp_mat = materials.add(Color::BLACK);
 
-    let mut file = File::open("assets/maps/map00.txt").unwrap();
-
-    let mut content = String::new();
-
-    file.read_to_string(&mut content).unwrap();
-
-    let tilemap = content.split("\n");
-
-    for (y, tile) in tilemap.into_iter().enumerate() {
-        for (x, elem) in tile.chars().enumerate() {
-            match elem {
-                '_' => {
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: horizontal_wall.clone(),
-                            material: material.clone(),
-                            transform: Transform::from_xyz(x as f32 - 64., 0.9, y as f32 - 64.),
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(0.6, 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                }
-                '|' => {
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: vertical_wall.clone(),
-                            material: material.clone(),
-                            transform: Transform::from_xyz(x as f32 - 64., 0.9, y as f32 - 64.),
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(0.6, 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                }
-                '/' => {
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: diagonal_l_wall.clone(),
-                            material: material.clone(),
-                            // transform: Transform::from_xyz(x as f32 -64., 0.9, y as f32 -64.),
-                            transform: Transform {
-                                translation: Vec3::new(x as f32 - 64., 0.9, y as f32 - 64.),
-                                rotation: Quat::from_rotation_y(45_f32.to_radians()),
-                                scale: Vec3::splat(1.0),
-                            },
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(TILE_SIZE.sqrt(), 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                }
-                '\\' => {
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: diagonal_l_wall.clone(),
-                            material: material.clone(),
-                            // transform: Transform::from_xyz(x as f32 -64., 0.9, y as f32 -64.),
-                            transform: Transform {
-                                translation: Vec3::new(x as f32 - 64., 0.9, y as f32 - 64.),
-                                rotation: Quat::from_rotation_y(135_f32.to_radians()),
-                                scale: Vec3::splat(1.0),
-                            },
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(TILE_SIZE.sqrt(), 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                }
-                '0' => {
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: horizontal_wall.clone(),
-                            material: material.clone(),
-                            transform: Transform::from_xyz(x as f32 - 64., 0.9, y as f32 - 64.),
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(0.6, 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                    commands.spawn((
-                        MaterialMeshBundle {
-                            mesh: vertical_wall.clone(),
-                            material: material.clone(),
-                            transform: Transform::from_xyz(x as f32 - 64., 0.9, y as f32 - 64.),
-                            ..default()
-                        },
-                        // Collider cubique
-                        Collider::cuboid(0.6, 5., 0.6),
-                        RigidBody::Fixed, // Le cube est immobile
-                    )).insert(ActiveEvents::COLLISION_EVENTS);
-                }
-                _ => {}
-            }
-        }
+    let (walls, _) = load_map(MAP_PATH);
+    for (shape, transform) in walls {
+        let mesh = match shape {
+            WallShape::Horizontal => horizontal_wall.clone(),
+            WallShape::Vertical => vertical_wall.clone(),
+            WallShape::Diagonal => diagonal_l_wall.clone(),
+        };
+        commands.spawn((
+            MaterialMeshBundle {
+                mesh,
+                material: material.clone(),
+                transform,
+                ..default()
+            },
+            // Collider cubique
+            shape.collider(),
+            RigidBody::Fixed, // Le cube est immobile
+        )).insert(ActiveEvents::COLLISION_EVENTS);
     }
 
     // The world model camera will render the floor and the cubes spawned in this system.
@@ -275,3 +282,34 @@ pub fn spawn_lights(mut commands: Commands) {
     //     ..default()
     // });
 }
+
+/// Colliders de la map sans mesh, pour le serveur qui n'a pas de rendu
+pub fn spawn_map_colliders(mut commands: Commands) {
+    let (walls, spawn_points) = load_map(MAP_PATH);
+    commands.insert_resource(SpawnPoints {
+        points: spawn_points,
+    });
+    for (shape, transform) in walls {
+        commands.spawn((
+            TransformBundle::from_transform(transform),
+            shape.collider(),
+            RigidBody::Fixed,
+        ));
+    }
+
+    commands
+        .spawn((
+            TransformBundle::default(),
+            Collider::cuboid(64., 0.1, 64.),
+            RigidBody::Fixed,
+        ))
+        .insert(Ground);
+
+    commands
+        .spawn((
+            TransformBundle::from_transform(Transform::from_xyz(0.0, 10.0, 0.0)),
+            Collider::cuboid(64., 0.1, 64.),
+            RigidBody::Fixed,
+        ))
+        .insert(Roof);
+}
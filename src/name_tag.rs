@@ -0,0 +1,232 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::render::render_resource::Face;
+use bevy_rapier3d::prelude::{QueryFilter, RapierContext};
+
+use crate::camera::WorldModelCamera;
+use crate::client::Entities;
+use crate::health::Health;
+use crate::player::{Affiliation, Player, Relationship};
+
+// Le nom flotte un peu au dessus de la capsule (demi-hauteur 1.2)
+const NAME_TAG_HEIGHT: f32 = 1.5;
+// Plein jusqu'a `FADE_START`, invisible a partir de `MAX_DISTANCE`
+const NAME_TAG_FADE_START: f32 = 20.0;
+const NAME_TAG_MAX_DISTANCE: f32 = 40.0;
+const NAME_TAG_WIDTH: f32 = 200.0;
+const HEALTH_BAR_WIDTH: f32 = 60.0;
+// Epaisseur du contour autour de la capsule d'un joueur distant (rayon 0.3)
+const OUTLINE_WIDTH: f32 = 0.04;
+
+/// Nom affiche au dessus d'un joueur distant, avec ses enfants pour les mettre a jour sans les chercher
+#[derive(Debug, Component)]
+pub struct NameTag {
+    pub target: Entity,
+    text: Entity,
+    health_bar: Entity,
+    health_fill: Entity,
+}
+
+/// Contour d'un joueur distant, enfant de sa capsule, a la couleur du lien avec le joueur local
+#[derive(Debug, Component)]
+pub struct Outline;
+
+/// Capsule un peu plus grande dont seules les faces arriere sont dessinees :
+/// elle ne depasse de la capsule du joueur que sur les bords
+pub fn outline(
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) -> impl Bundle {
+    (
+        PbrBundle {
+            mesh: meshes.add(Capsule3d::new(0.3 + OUTLINE_WIDTH, 1.8)),
+            material: materials.add(StandardMaterial {
+                base_color: Relationship::Enemy.color(),
+                unlit: true,
+                cull_mode: Some(Face::Front),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        Outline,
+        NotShadowCaster,
+    )
+}
+
+/// Opacite du nom d'un joueur a `distance` de la camera
+pub fn fade(distance: f32) -> f32 {
+    ((NAME_TAG_MAX_DISTANCE - distance) / (NAME_TAG_MAX_DISTANCE - NAME_TAG_FADE_START))
+        .clamp(0.0, 1.0)
+}
+
+fn spawn_name_tag(commands: &mut Commands, target: Entity) {
+    let text = commands
+        .spawn(TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        ))
+        .id();
+    let health_fill = commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgb(0.2, 0.9, 0.2)),
+            ..default()
+        })
+        .id();
+    let health_bar = commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(HEALTH_BAR_WIDTH),
+                height: Val::Px(5.0),
+                margin: UiRect::top(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .add_child(health_fill)
+        .id();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(NAME_TAG_WIDTH),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            NameTag {
+                target,
+                text,
+                health_bar,
+                health_fill,
+            },
+        ))
+        .push_children(&[text, health_bar]);
+}
+
+/// Place le nom de chaque joueur distant au dessus de sa tete, cache s'il est mort,
+/// trop loin ou derriere un mur. Les coequipiers ont aussi une barre de vie.
+#[allow(clippy::too_many_arguments)]
+pub fn update_name_tags(
+    mut commands: Commands,
+    entities: Res<Entities>,
+    rapier_context: Res<RapierContext>,
+    local_player: Query<(Entity, &Affiliation), With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<WorldModelCamera>>,
+    remote_players: Query<(&GlobalTransform, &Health, &Visibility, &Affiliation), Without<Node>>,
+    tags: Query<(Entity, &NameTag)>,
+    mut nodes: Query<(&mut Style, &mut Visibility, Option<&mut BackgroundColor>), With<Node>>,
+    mut texts: Query<&mut Text>,
+) {
+    let (Ok((local_entity, local_affiliation)), Ok((camera, camera_transform))) =
+        (local_player.get_single(), camera.get_single())
+    else {
+        return;
+    };
+
+    // Un nom par joueur distant, retire avec lui
+    for &target in entities.players.values() {
+        if !tags.iter().any(|(_, tag)| tag.target == target) {
+            spawn_name_tag(&mut commands, target);
+        }
+    }
+
+    let eye = camera_transform.translation();
+    let filter = QueryFilter::only_fixed().exclude_collider(local_entity);
+    for (tag_entity, tag) in tags.iter() {
+        let Ok((transform, health, visibility, affiliation)) = remote_players.get(tag.target)
+        else {
+            commands.entity(tag_entity).despawn_recursive();
+            continue;
+        };
+        let head = transform.translation() + Vec3::Y * NAME_TAG_HEIGHT;
+        let to_head = head - eye;
+        let distance = to_head.length();
+        let alpha = fade(distance);
+        // Seuls les murs cachent un nom, pas les autres joueurs
+        let occluded = || {
+            rapier_context
+                .cast_ray(eye, to_head / distance, distance, true, filter)
+                .is_some()
+        };
+        let position = camera.world_to_viewport(camera_transform, head);
+        let shown =
+            *visibility != Visibility::Hidden && alpha > 0.0 && position.is_some() && !occluded();
+
+        let Ok((mut style, mut tag_visibility, _)) = nodes.get_mut(tag_entity) else {
+            continue;
+        };
+        *tag_visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let Some(position) = position.filter(|_| shown) else {
+            continue;
+        };
+        style.left = Val::Px(position.x - NAME_TAG_WIDTH / 2.0);
+        style.top = Val::Px(position.y - 30.0);
+
+        let relationship = Relationship::between(local_affiliation, affiliation);
+        if let Ok(mut text) = texts.get_mut(tag.text) {
+            let name = entities
+                .players
+                .iter()
+                .find(|(_, entity)| **entity == tag.target)
+                .map(|(id, _)| entities.name(*id))
+                .unwrap_or_default();
+            if text.sections[0].value != name {
+                text.sections[0].value = name;
+            }
+            text.sections[0].style.color = relationship.color().with_alpha(alpha);
+        }
+        if let Ok((_, mut bar_visibility, _)) = nodes.get_mut(tag.health_bar) {
+            *bar_visibility = if relationship == Relationship::Enemy {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            };
+        }
+        if let Ok((mut fill_style, _, Some(mut fill_color))) = nodes.get_mut(tag.health_fill) {
+            fill_style.width = Val::Percent(100.0 * health.current / health.max);
+            fill_color.0.set_alpha(alpha);
+        }
+    }
+}
+
+/// Recolore les contours quand l'equipe du joueur local ou d'un joueur distant change
+pub fn update_outlines(
+    local_player: Query<&Affiliation, With<Player>>,
+    remote_players: Query<&Affiliation, Without<Player>>,
+    outlines: Query<(&Parent, &Handle<StandardMaterial>), With<Outline>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Ok(local_affiliation) = local_player.get_single() else {
+        return;
+    };
+    for (parent, material) in outlines.iter() {
+        let Ok(affiliation) = remote_players.get(parent.get()) else {
+            continue;
+        };
+        let color = Relationship::between(local_affiliation, affiliation).color();
+        // `get_mut` marquerait le materiau comme modifie a chaque frame
+        if materials.get(material).is_some_and(|material| material.base_color != color) {
+            if let Some(material) = materials.get_mut(material) {
+                material.base_color = color;
+            }
+        }
+    }
+}
@@ -1,9 +1,15 @@
-use renet::{transport::NetcodeClientTransport, ClientId, RenetClient};
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use renet::{ClientId, RenetClient};
 
 use bevy::{input::mouse::MouseMotion, prelude::*};
 use bevy_rapier3d::{control::KinematicCharacterController, prelude::*};
+use serde::{Deserialize, Serialize};
 
+use crate::ammo::Ammo;
 use crate::client::client_send_input;
+use crate::weapon::{WeaponId, DEFAULT_WEAPON};
 
 #[derive(Debug, Component)]
 pub struct Player;
@@ -13,21 +19,278 @@ use crate::keybind::*;
 pub struct PlayerData {
     pub id: ClientId,
     pub position: Transform,
+    // Entite simulee par le serveur pour ce joueur
+    pub entity: Option<Entity>,
+    // Commandes recues du client (numero de sequence, commande), pas encore simulees
+    pub pending_inputs: VecDeque<(u32, PlayerInput)>,
+    // Derniere commande simulee, renvoyee au client pour la reconciliation
+    pub last_input_sequence: u32,
+    // Dernier WorldSnapshot recu par le client, reference du prochain delta
+    pub snapshot_ack: u32,
+    // Tick du serveur auquel le joueur revient, `Some` tant qu'il est mort
+    pub respawn_tick: Option<u32>,
+    pub stats: PlayerStats,
+    // Joueurs qui l'ont blesse depuis son dernier respawn, pour les assists
+    pub damaged_by: Vec<ClientId>,
+    // Aller-retour en millisecondes, releve regulierement pour le tableau des scores
+    pub ping: u32,
+    // Pseudo valide et unique dans le Lobby, choisi a la connexion
+    pub name: String,
+    pub affiliation: Affiliation,
+    // Munitions qui font foi, par arme. Une arme absente a ses munitions de depart.
+    pub ammo: HashMap<WeaponId, Ammo>,
+    // Arme en cours de rechargement et tick du serveur ou le chargeur sera plein
+    pub reload: Option<(WeaponId, u32)>,
+    // Arme en main, annoncee par le client a chaque changement
+    pub weapon: WeaponId,
+    // Tick du serveur a partir duquel l'arme en main peut tirer
+    pub weapon_ready_tick: u32,
+    // Tick du dernier tir accepte, compte a la cadence de l'arme : il peut etre en avance sur
+    // le tick du serveur quand des tirs arrivent groupes
+    pub last_shot_tick: Option<f64>,
+}
+
+/// Compteurs de la partie, tenus par le serveur
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+}
+
+// Points par kill et par assist au tableau des scores
+const KILL_SCORE: u32 = 100;
+const ASSIST_SCORE: u32 = 50;
+
+/// Equipe et escouade d'un joueur, choisies par le serveur a la connexion.
+/// Sans `--teams`, tout le monde est sans equipe, donc ennemi de tout le monde.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Component)]
+pub struct Affiliation {
+    pub team: Option<u8>,
+    pub squad: Option<u8>,
+}
+
+/// Lien entre le joueur local et un autre joueur, donne la couleur de son nom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    Enemy,
+    Teammate,
+    Squad,
+}
+
+impl Relationship {
+    pub fn between(local: &Affiliation, other: &Affiliation) -> Self {
+        match (local.team, other.team) {
+            (Some(team), Some(other_team)) if team == other_team => {
+                if local.squad.is_some() && local.squad == other.squad {
+                    Relationship::Squad
+                } else {
+                    Relationship::Teammate
+                }
+            }
+            _ => Relationship::Enemy,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Relationship::Enemy => Color::srgb(1.0, 0.25, 0.2),
+            Relationship::Teammate => Color::srgb(0.3, 0.6, 1.0),
+            Relationship::Squad => Color::srgb(0.3, 1.0, 0.4),
+        }
+    }
+}
+
+/// Nom affiche pour un joueur dont on ne connait pas encore le pseudo
+pub fn placeholder_name(id: ClientId) -> String {
+    format!("Player {}", id)
+}
+
+impl PlayerStats {
+    pub fn score(&self) -> u32 {
+        self.kills * KILL_SCORE + self.assists * ASSIST_SCORE
+    }
+
+    /// Sans mort, le ratio vaut le nombre de kills
+    pub fn kd_ratio(&self) -> f32 {
+        self.kills as f32 / self.deaths.max(1) as f32
+    }
 }
 
 impl PlayerData {
+    pub fn is_dead(&self) -> bool {
+        self.respawn_tick.is_some()
+    }
+
     pub fn new(id: ClientId) -> PlayerData {
         PlayerData {
             id,
             position: Transform::from_xyz(0.0, 1.3, 0.0),
+            entity: None,
+            pending_inputs: VecDeque::new(),
+            last_input_sequence: 0,
+            snapshot_ack: 0,
+            respawn_tick: None,
+            stats: PlayerStats::default(),
+            damaged_by: Vec::new(),
+            ping: 0,
+            name: placeholder_name(id),
+            affiliation: Affiliation::default(),
+            ammo: HashMap::new(),
+            reload: None,
+            weapon: DEFAULT_WEAPON,
+            weapon_ready_tick: 0,
+            last_shot_tick: None,
+        }
+    }
+}
+
+/// Commande envoyee au serveur a chaque tick fixe : le `MovementInput` et l'orientation du regard
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub movement: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl PlayerInput {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch)
+    }
+
+    /// Commande ramenee dans ce qu'un client honnete peut envoyer : pas plus vite qu'en sprint,
+    /// un saut tout ou rien, le regard sans retournement. `None` si une valeur n'est pas finie.
+    pub fn sanitized(&self) -> Option<PlayerInput> {
+        if !self.movement.is_finite() || !self.yaw.is_finite() || !self.pitch.is_finite() {
+            return None;
+        }
+        // Une commande honnete ressort a l'identique, sinon la prediction du client diverge
+        let mut horizontal = Vec2::new(self.movement.x, self.movement.z);
+        if horizontal.length() > SPRINT_MULTIPLIER + 1e-3 {
+            horizontal = horizontal.clamp_length_max(SPRINT_MULTIPLIER);
+        }
+        let jump = if self.movement.y > 0.0 { 1.0 } else { 0.0 };
+        let yaw = if (-PI..=PI).contains(&self.yaw) {
+            self.yaw
+        } else {
+            (self.yaw + PI).rem_euclid(TAU) - PI
+        };
+        Some(PlayerInput {
+            movement: Vec3::new(horizontal.x, jump, horizontal.y),
+            yaw,
+            pitch: self.pitch.clamp(-FRAC_PI_2, FRAC_PI_2),
+        })
+    }
+}
+
+/// Etat vertical du joueur, le meme cote client et cote serveur
+#[derive(Debug, Default, Clone, Copy, Component, Serialize, Deserialize)]
+pub struct MovementState {
+    pub vertical_movement: f32,
+    pub grounded_timer: f32,
+    pub grounded: bool,
+}
+
+/// Les commandes sont jouees a ce pas fixe par le client et par le serveur,
+/// quelle que soit la frequence des ticks du serveur
+pub const INPUT_HZ: f64 = 60.0;
+pub const INPUT_DT: f32 = 1.0 / INPUT_HZ as f32;
+
+const INPUT_HISTORY_SIZE: usize = 128;
+// Au dela de cette erreur on se teleporte au lieu de lisser
+const MAX_SMOOTHED_CORRECTION: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct InputRecord {
+    pub sequence: u32,
+    pub input: PlayerInput,
+}
+
+/// Etat du joueur local renvoye par le serveur apres la commande `sequence`
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct InputAck {
+    pub sequence: u32,
+    pub position: Vec3,
+    pub movement: MovementState,
+}
+
+/// Commandes deja predites par le joueur local, indexees par numero de sequence.
+/// On les garde jusqu'a ce que le serveur confirme qu'il les a simulees.
+#[derive(Debug, Component)]
+pub struct InputHistory {
+    records: [Option<InputRecord>; INPUT_HISTORY_SIZE],
+    next_sequence: u32,
+    // Derniere confirmation du serveur, pas encore reconciliee
+    pub pending_ack: Option<InputAck>,
+    last_acked: u32,
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        InputHistory {
+            records: [None; INPUT_HISTORY_SIZE],
+            // 0 veut dire "aucune commande simulee" cote serveur
+            next_sequence: 1,
+            pending_ack: None,
+            last_acked: 0,
         }
     }
 }
 
+impl InputHistory {
+    pub fn push(&mut self, input: PlayerInput) -> u32 {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.records[sequence as usize % INPUT_HISTORY_SIZE] = Some(InputRecord {
+            sequence,
+            input,
+        });
+        sequence
+    }
+
+    /// Les confirmations arrivent sur un canal non fiable : une confirmation en retard est ignoree
+    pub fn receive_ack(&mut self, ack: InputAck) {
+        if ack.sequence > self.last_acked {
+            self.last_acked = ack.sequence;
+            self.pending_ack = Some(ack);
+        }
+    }
+
+    /// Les `max` dernieres commandes que le serveur n'a pas encore confirmees
+    pub fn to_send(&self, max: usize) -> Vec<InputRecord> {
+        let mut records = self.unacknowledged(self.last_acked);
+        records.drain(..records.len().saturating_sub(max));
+        records
+    }
+
+    /// Commandes plus recentes que `acked`, dans l'ordre ou elles ont ete jouees
+    pub fn unacknowledged(&self, acked: u32) -> Vec<InputRecord> {
+        let oldest = self
+            .next_sequence
+            .saturating_sub(INPUT_HISTORY_SIZE as u32)
+            .max(acked.saturating_add(1));
+        (oldest..self.next_sequence)
+            .filter_map(|sequence| {
+                self.records[sequence as usize % INPUT_HISTORY_SIZE]
+                    .filter(|record| record.sequence == sequence)
+            })
+            .collect()
+    }
+}
+
+/// Ecart entre l'ancienne position predite et la position corrigee,
+/// rattrape petit a petit par la camera pour ne pas voir de saut
+#[derive(Debug, Default, Component)]
+pub struct PredictionCorrection {
+    pub offset: Vec3,
+}
+
 const MOUSE_SENSITIVITY: f32 = 0.3;
 const GROUND_TIMER: f32 = 0.5;
 const MOVEMENT_SPEED: f32 = 8.0;
 const JUMP_SPEED: f32 = 20.0;
+// Vitesse en sprint par rapport a la marche, le serveur plafonne les commandes a cette norme
+pub const SPRINT_MULTIPLIER: f32 = 2.0;
 const GRAVITY: f32 = -9.81;
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Resource)]
@@ -37,6 +300,15 @@ pub enum PlayerState {
     Dead,
 }
 
+/// Temps avant le respawn annonce par le serveur, present seulement quand le joueur est mort
+#[derive(Debug, Resource)]
+pub struct RespawnCountdown {
+    pub timer: Timer,
+}
+
+#[derive(Debug, Component)]
+pub struct DeathText;
+
 /// Keyboard input vector
 #[derive(Default, Resource, Deref, DerefMut)]
 pub struct MovementInput(Vec3);
@@ -52,25 +324,29 @@ pub fn handle_input(
     mut look: ResMut<LookInput>,
     mut mouse_events: EventReader<MouseMotion>,
 ) {
+    // Recalcule a chaque frame : les touches tenues valent pour tous les ticks fixes de la frame
+    let mut direction = Vec3::ZERO;
     if keyboard.pressed(key_binds.move_forward) {
-        movement.z -= 1.0;
+        direction.z -= 1.0;
     }
     if keyboard.pressed(key_binds.move_backward) {
-        movement.z += 1.0;
+        direction.z += 1.0;
     }
     if keyboard.pressed(key_binds.move_left) {
-        movement.x -= 1.0;
+        direction.x -= 1.0;
     }
     if keyboard.pressed(key_binds.move_right) {
-        movement.x += 1.0;
+        direction.x += 1.0;
     }
-    **movement = movement.normalize_or_zero();
+    direction = direction.normalize_or_zero();
     if keyboard.pressed(key_binds.sprint) {
-        **movement *= 2.0;
+        direction *= SPRINT_MULTIPLIER;
     }
-    if keyboard.pressed(key_binds.jump) {
-        movement.y = 1.0;
+    // Un saut garde jusqu'au prochain tick fixe n'est pas perdu si la touche est deja relachee
+    if movement.y > 0.0 || keyboard.pressed(key_binds.jump) {
+        direction.y = 1.0;
     }
+    **movement = direction;
 
     for event in mouse_events.read() {
         look.x -= event.delta.x * MOUSE_SENSITIVITY;
@@ -79,48 +355,190 @@ pub fn handle_input(
     }
 }
 
+/// Collider du joueur, le meme pour le joueur local et pour les joueurs simules par le serveur
+pub fn player_collider() -> Collider {
+    Collider::round_cylinder(0.9, 0.3, 0.2)
+}
+
+pub fn player_controller() -> KinematicCharacterController {
+    KinematicCharacterController {
+        custom_mass: Some(5.0),
+        up: Vec3::Y,
+        offset: CharacterLength::Absolute(0.01),
+        slide: true,
+        autostep: Some(CharacterAutostep {
+            max_height: CharacterLength::Relative(0.3),
+            min_width: CharacterLength::Relative(0.5),
+            include_dynamic_bodies: false,
+        }),
+        // Don’t allow climbing slopes larger than 45 degrees.
+        max_slope_climb_angle: 45.0_f32.to_radians(),
+        // Automatically slide down on slopes smaller than 30 degrees.
+        min_slope_slide_angle: 30.0_f32.to_radians(),
+        apply_impulse_to_dynamic_bodies: true,
+        snap_to_ground: None,
+        ..default()
+    }
+}
+
+/// Avance un joueur d'un tick avec sa commande.
+/// Le client et le serveur passent tous les deux par ici pour obtenir la meme position.
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_movement(
+    context: &mut RapierContext,
+    entity: Entity,
+    transform: &mut Transform,
+    collider: &Collider,
+    controller: &KinematicCharacterController,
+    state: &mut MovementState,
+    input: &PlayerInput,
+    delta_time: f32,
+) {
+    let rotation = input.rotation();
+    // Retrieve input
+    let mut movement = Vec3::new(input.movement.x, 0.0, input.movement.z) * MOVEMENT_SPEED;
+    let jump_speed = input.movement.y * JUMP_SPEED;
+    // Check physics ground check
+    if state.grounded {
+        state.grounded_timer = GROUND_TIMER;
+        state.vertical_movement = 0.0;
+    }
+    // If we are grounded we can jump
+    if state.grounded_timer > 0.0 {
+        state.grounded_timer -= delta_time;
+        // If we jump we clear the grounded tolerance
+        if jump_speed > 0.0 {
+            state.vertical_movement = jump_speed;
+            state.grounded_timer = 0.0;
+        }
+    }
+    movement.y = state.vertical_movement;
+    let mass = controller.custom_mass.unwrap_or(1.0);
+    state.vertical_movement += GRAVITY * delta_time * mass;
+
+    let options = MoveShapeOptions {
+        up: controller.up,
+        offset: controller.offset,
+        slide: controller.slide,
+        autostep: controller.autostep,
+        max_slope_climb_angle: controller.max_slope_climb_angle,
+        min_slope_slide_angle: controller.min_slope_slide_angle,
+        apply_impulse_to_dynamic_bodies: controller.apply_impulse_to_dynamic_bodies,
+        snap_to_ground: controller.snap_to_ground,
+        normal_nudge_factor: controller.normal_nudge_factor,
+    };
+    let output = context.move_shape(
+        rotation * (movement * delta_time),
+        collider,
+        transform.translation,
+        rotation,
+        mass,
+        &options,
+        QueryFilter::new().exclude_collider(entity).exclude_sensors(),
+        |_| {},
+    );
+    transform.translation += output.effective_translation;
+    state.grounded = output.grounded;
+}
+
+#[allow(clippy::type_complexity)]
 pub fn player_movement(
-    time: Res<Time>,
     mut input: ResMut<MovementInput>,
-    mut player: Query<(
-        &mut Transform,
-        &mut KinematicCharacterController,
-        Option<&KinematicCharacterControllerOutput>,
-    )>,
-    mut vertical_movement: Local<f32>,
-    mut grounded_timer: Local<f32>,
+    mut context: ResMut<RapierContext>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &Collider,
+            &KinematicCharacterController,
+            &mut MovementState,
+            &mut InputHistory,
+        ),
+        With<Player>,
+    >,
     client: ResMut<RenetClient>,
-    transport: ResMut<NetcodeClientTransport>,
+    player_state: Res<PlayerState>,
 ) {
-    let input_pressed = input.length() > 0.0;
-    let Ok((transform, mut controller, output)) = player.get_single_mut() else {
+    let Ok((entity, mut transform, collider, controller, mut state, mut history)) =
+        player.get_single_mut()
+    else {
         return;
     };
-    if input_pressed {
-        let delta_time = time.delta_seconds();
-        // Retrieve input
-        let mut movement = Vec3::new(input.x, 0.0, input.z) * MOVEMENT_SPEED;
-        let jump_speed = input.y * JUMP_SPEED;
-        // Clear input
-        **input = Vec3::ZERO;
-        // Check physics ground check
-        if output.map(|o| o.grounded).unwrap_or(false) {
-            *grounded_timer = GROUND_TIMER;
-            *vertical_movement = 0.0;
-        }
-        // If we are grounded we can jump
-        if *grounded_timer > 0.0 {
-            *grounded_timer -= delta_time;
-            // If we jump we clear the grounded tolerance
-            if jump_speed > 0.0 {
-                *vertical_movement = jump_speed;
-                *grounded_timer = 0.0;
-            }
-        }
-        movement.y = *vertical_movement;
-        *vertical_movement += GRAVITY * delta_time * controller.custom_mass.unwrap_or(1.0);
-        controller.translation = Some(transform.rotation * (movement * delta_time));
-        client_send_input(transform.translation, client, transport);
+    let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+    // Mort : le serveur ignore aussi le deplacement, la prediction reste juste
+    let movement = match *player_state {
+        PlayerState::Alive => **input,
+        PlayerState::Dead => Vec3::ZERO,
+    };
+    let command = PlayerInput {
+        movement,
+        yaw,
+        pitch,
+    };
+    // Seul le saut est consomme : le deplacement tenu sert aussi aux ticks suivants de la frame
+    input.y = 0.0;
+    // Prediction locale, le serveur rejoue la meme commande et fait foi
+    history.push(command);
+    simulate_movement(
+        &mut context,
+        entity,
+        &mut transform,
+        collider,
+        controller,
+        &mut state,
+        &command,
+        INPUT_DT,
+    );
+    client_send_input(&history, client);
+}
+
+/// Repart de la position confirmee par le serveur et rejoue les commandes qu'il n'a pas encore vues
+#[allow(clippy::type_complexity)]
+pub fn reconcile_player(
+    mut context: ResMut<RapierContext>,
+    mut player: Query<
+        (
+            Entity,
+            &mut Transform,
+            &Collider,
+            &KinematicCharacterController,
+            &mut MovementState,
+            &mut InputHistory,
+            &mut PredictionCorrection,
+        ),
+        With<Player>,
+    >,
+) {
+    let Ok((entity, mut transform, collider, controller, mut state, mut history, mut correction)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    let Some(ack) = history.pending_ack.take() else {
+        return;
+    };
+    let predicted_position = transform.translation;
+
+    transform.translation = ack.position;
+    *state = ack.movement;
+    for record in history.unacknowledged(ack.sequence) {
+        simulate_movement(
+            &mut context,
+            entity,
+            &mut transform,
+            collider,
+            controller,
+            &mut state,
+            &record.input,
+            INPUT_DT,
+        );
+    }
+
+    let error = predicted_position - transform.translation;
+    if error.length() > MAX_SMOOTHED_CORRECTION {
+        correction.offset = Vec3::ZERO;
+    } else {
+        correction.offset += error;
     }
 }
 
@@ -139,17 +557,36 @@ pub fn player_look(
     transform.rotation = Quat::from_axis_angle(Vec3::X, input.y.to_radians());
 }
 
+/// Ecran de mort avec le compte a rebours du respawn, enleve quand le serveur fait revenir le joueur
 pub fn la_mooooooooooort(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut player_state: ResMut<PlayerState>,
+    time: Res<Time>,
+    player_state: Res<PlayerState>,
+    countdown: Option<ResMut<RespawnCountdown>>,
+    mut death_text: Query<(Entity, &mut Text), With<DeathText>>,
 ) {
-    if *player_state == PlayerState::Dead {
-        println!("You are dead");
-        commands.spawn(TextBundle {
+    if *player_state == PlayerState::Alive {
+        for (entity, _) in death_text.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    let remaining = countdown.map_or(0.0, |mut countdown| {
+        countdown.timer.tick(time.delta());
+        countdown.timer.remaining_secs().ceil()
+    });
+    let value = format!("You are dead\nRespawn in {}", remaining);
+    if let Ok((_, mut text)) = death_text.get_single_mut() {
+        text.sections[0].value = value;
+        return;
+    }
+    println!("You are dead");
+    commands.spawn((
+        TextBundle {
             text: Text {
                 sections: vec![TextSection {
-                    value: "You are dead".to_string(),
+                    value,
                     style: TextStyle {
                         font: asset_server.load("fonts/FiraSans-Bold.ttf"),
                         font_size: 40.0,
@@ -160,7 +597,7 @@ pub fn la_mooooooooooort(
                 ..Default::default()
             },
             ..Default::default()
-        });
-        *player_state = PlayerState::Alive;
-    }
+        },
+        DeathText,
+    ));
 }
@@ -1,22 +1,37 @@
-use std::time::SystemTime;
-
 use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 // use bevy_rapier3d::{prelude::{ActiveEvents, Collider, CollisionEvent}, rapier::prelude::RigidBody};
 use bevy_rapier3d::prelude::*;
 use rand::Rng;
-use renet::{transport::NetcodeClientTransport, RenetClient};
+use renet::{transport::NetcodeClientTransport, ClientId, RenetClient};
+
+use crate::{
+    client::{client_send_projectile_position, ServerClock},
+    player::Player,
+    weapon::{Weapon, WeaponId},
+    weapon_definition::WeaponDefinition,
+};
+
+/// Identifiant donne par le serveur a chaque projectile, unique pour toute la partie
+pub type ProjectileId = u32;
 
-use crate::{client::client_send_projectile_position, player::{Player, PlayerState}, weapon::Weapon};
+/// Rayon de la balle, affichee et balayee
+pub const PROJECTILE_RADIUS: f32 = 0.01;
 
 #[derive(Debug, Component)]
-pub struct Projectile;
+pub struct Projectile {
+    pub owner: ClientId,
+    pub weapon: WeaponId,
+    // Numero du tir chez le tireur, pour retrouver son projectile quand le serveur lui donne un id
+    pub shot: u32,
+    // `None` tant que le serveur n'a pas confirme le tir
+    pub network_id: Option<ProjectileId>,
+}
 
 #[derive(Debug, Component)]
 pub struct ProjectilePosition {
     pub direction: Vec3,
     pub speed: f32,
-    pub projectile_id: u64,
 }
 
 #[derive(Debug, Component)]
@@ -24,6 +39,62 @@ pub struct Lifetime {
     pub timer: Timer,
 }
 
+/// Bout du canon dans le monde, l'arme etant placee par rapport au joueur
+pub fn muzzle(weapon_transform: &Transform, player_transform: &Transform) -> Vec3 {
+    let weapon_end_offset = weapon_transform.rotation * Vec3::new(0.0, 0., -0.5);
+    let weapon_end_position = weapon_transform.translation + weapon_end_offset;
+    player_transform.translation + player_transform.rotation * weapon_end_position
+}
+
+/// Decalage aleatoire d'au plus `spread` sur chaque axe
+pub fn apply_spread(direction: Vec3, spread: f32) -> Vec3 {
+    if spread <= 0.0 {
+        return direction;
+    }
+    let mut rng = rand::thread_rng();
+    direction
+        + Vec3::new(
+            rng.gen_range(-spread..spread),
+            rng.gen_range(-spread..spread),
+            rng.gen_range(-spread..spread),
+        )
+}
+
+/// Balle affichee chez un client, la sienne comme celles des autres. Sans corps ni collider :
+/// `update_projectiles` la deplace en balayant son trajet, la physique ne la pousse pas
+/// et elle ne pousse personne.
+pub fn projectile_bundle(
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    projectile: Projectile,
+    position: Vec3,
+    direction: Vec3,
+    definition: &WeaponDefinition,
+) -> impl Bundle {
+    (
+        PbrBundle {
+            mesh: meshes.add(Sphere::new(PROJECTILE_RADIUS)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::srgb(1., 1., 0.),
+                emissive: LinearRgba::rgb(10.0, 10., 0.),
+                ..Default::default()
+            }),
+            transform: Transform::from_translation(position),
+            ..Default::default()
+        },
+        projectile,
+        ProjectilePosition {
+            direction,
+            speed: definition.projectile_speed,
+        },
+        Lifetime {
+            timer: Timer::from_seconds(definition.projectile_lifetime, TimerMode::Once),
+        },
+        NotShadowCaster,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_projectile(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -31,72 +102,93 @@ pub fn spawn_projectile(
     weapon_query: Query<(&Transform, &Parent), With<Weapon>>,
     player_query: Query<&Transform, With<Player>>,
     mut client: ResMut<RenetClient>,
-    transport: ResMut<NetcodeClientTransport>,
+    transport: Res<NetcodeClientTransport>,
+    clock: Res<ServerClock>,
+    time: Res<Time>,
+    shot: u32,
+    definition: &WeaponDefinition,
 ) {
     for (weapon_transform, parent) in weapon_query.iter() {
         if let Ok(player_transform) = player_query.get(parent.get()) {
-            let weapon_end_offset = weapon_transform.rotation * Vec3::new(0.0, 0., -0.5);
-            let weapon_end_position = weapon_transform.translation + weapon_end_offset;
-
-            let spawn_position =
-                player_transform.translation + player_transform.rotation * weapon_end_position;
+            let spawn_position = muzzle(weapon_transform, player_transform);
 
             let mut direction = player_transform.forward().as_vec3(); // Forward direction of the player
             direction.y += 0.03;
+            let direction = apply_spread(direction, definition.spread);
 
-            let mut rng = rand::thread_rng();
-            let random_offset = Vec3::new(
-                rng.gen_range(-0.01..0.01),
-                rng.gen_range(-0.01..0.01),
-                rng.gen_range(-0.01..0.01),
+            let projectile = Projectile {
+                owner: transport.client_id(),
+                weapon: definition.id,
+                shot,
+                network_id: None,
+            };
+            commands.spawn(projectile_bundle(
+                &mut meshes,
+                &mut materials,
+                projectile,
+                spawn_position,
+                direction,
+                definition,
+            ));
+            client_send_projectile_position(
+                shot,
+                definition.id,
+                spawn_position,
+                &mut client,
+                direction,
+                clock.render_time(time.elapsed_seconds_f64()),
             );
-
-            direction += random_offset;
-
-            commands
-                .spawn(PbrBundle {
-                    mesh: meshes.add(Sphere::new(0.01)),
-                    material: materials.add(StandardMaterial {
-                        base_color: Color::srgb(1., 1., 0.),
-                        emissive: LinearRgba::rgb(10.0, 10., 0.),
-                        ..Default::default()
-                    }),
-                    transform: Transform::from_translation(spawn_position),
-                    ..Default::default()
-                })
-                .insert(Projectile)
-                .insert(ProjectilePosition {
-                    direction,
-                    speed: 50.0, // Vitesse du projectile
-                    // speed: 0.5,
-                    projectile_id: SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
-                })
-                .insert(Lifetime {
-                    timer: Timer::from_seconds(10.0, TimerMode::Once), // Durée de vie du projectile
-                })
-                .insert(Collider::ball(0.01))
-                .insert(RigidBody::Dynamic)
-                .insert(NotShadowCaster)
-                .insert(ActiveEvents::COLLISION_EVENTS);
-            client_send_projectile_position(spawn_position, &mut *client, &*transport, direction);
         }
     }
 }
 
+/// Premier mur ou joueur touche par une balle partie de `position` a `velocity` pendant `dt`,
+/// avec l'instant du choc. Ce que la balle touche deja au depart est ignore : elle peut sortir
+/// du joueur qui l'a tiree. Les projectiles n'ont pas de collider, ils ne s'arretent pas entre eux.
+pub fn sweep(
+    context: &RapierContext,
+    position: Vec3,
+    velocity: Vec3,
+    dt: f32,
+) -> Option<(Entity, f32)> {
+    let options = ShapeCastOptions {
+        max_time_of_impact: dt,
+        target_distance: 0.0,
+        stop_at_penetration: false,
+        compute_impact_geometry_on_penetration: false,
+    };
+    context
+        .cast_shape(
+            position,
+            Quat::IDENTITY,
+            velocity,
+            &Collider::ball(PROJECTILE_RADIUS),
+            options,
+            QueryFilter::default().exclude_sensors(),
+        )
+        .map(|(entity, hit)| (entity, hit.time_of_impact))
+}
+
+/// Avance les projectiles en balayant le trajet de la frame : meme avec un grand pas de temps,
+/// une balle s'arrete dans le premier mur ou joueur au lieu de passer au travers
 pub fn update_projectiles(
     time: Res<Time>,
     mut commands: Commands,
+    rapier_context: Res<RapierContext>,
     mut query: Query<
         (Entity, &mut Transform, &ProjectilePosition, &mut Lifetime),
         With<Projectile>,
     >,
 ) {
+    let dt = time.delta_seconds();
     for (entity, mut transform, projectile_position, mut lifetime) in query.iter_mut() {
-        let direction = projectile_position.direction;
-        transform.translation += direction * projectile_position.speed * time.delta_seconds();
+        let velocity = projectile_position.direction * projectile_position.speed;
+        if sweep(&rapier_context, transform.translation, velocity, dt).is_some() {
+            // Les touches sur les joueurs restent decidees par le serveur (PlayerHit)
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += velocity * dt;
 
         lifetime.timer.tick(time.delta());
         if lifetime.timer.finished() {
@@ -111,7 +203,6 @@ pub fn detect_collisions(
     projectile_query: Query<Entity, With<Projectile>>,
     wall_query: Query<Entity, (Without<Projectile>, Without<Player>)>,
     player_query: Query<Entity, With<Player>>,
-    mut player_state: ResMut<PlayerState>,
 ) {
     for event in collision_events.read() {
         // println!("Event detected");
@@ -131,13 +222,10 @@ pub fn detect_collisions(
                     //    println!("Projectile hit wall!");
                     // commands.entity(*entity2).despawn();
                 } else if is_projectile1 && is_player2 {
-                    *player_state = PlayerState::Dead;
-                    println!("Projectile hit player!");
+                    // Simple effet visuel : les touches sont decidees par le serveur (PlayerHit)
                     // commands.entity(*entity1).despawn();
                     // commands.entity(*entity2).despawn();
                 } else if is_projectile2 && is_player1 {
-                    *player_state = PlayerState::Dead;
-                    println!("Projectile hit player!");
                     // commands.entity(*entity2).despawn();
                     // commands.entity(*entity1).despawn();
                 } else {
@@ -0,0 +1,128 @@
+use std::{fmt, time::Duration};
+
+use bevy::prelude::Resource;
+use bincode::Options;
+use renet::{ChannelConfig, ConnectionConfig, SendType};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Taille max d'un message, en octets. Nos messages font quelques dizaines d'octets.
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Meme configuration des deux cotes. La limite empeche aussi un paquet
+/// d'annoncer une enorme longueur de Vec/String et de faire allouer le serveur.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_limit(MAX_MESSAGE_SIZE as u64)
+        .reject_trailing_bytes()
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    TooLarge(usize),
+    Malformed(bincode::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::TooLarge(size) => write!(
+                f,
+                "message too large ({} bytes, max {})",
+                size, MAX_MESSAGE_SIZE
+            ),
+            DecodeError::Malformed(error) => write!(f, "malformed message ({})", error),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Canaux renet, les memes dans les deux sens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    // Non fiable : positions et commandes envoyees a chaque tick, seule la plus recente compte
+    Movement,
+    // Fiable sans ordre : projectiles, touches, morts
+    Events,
+    // Fiable et ordonne : connexions, deconnexions, chat
+    Lobby,
+    // Fiable et ordonne : tirs, rechargements et changements d'arme du client. Le serveur
+    // doit les voir dans l'ordre, un tir depend de l'arme en main et de son chargeur
+    Actions,
+}
+
+impl Channel {
+    /// Ordre de lecture : un joueur doit etre connecte avant qu'on traite ses mouvements
+    pub const ALL: [Channel; 4] = [
+        Channel::Lobby,
+        Channel::Actions,
+        Channel::Events,
+        Channel::Movement,
+    ];
+
+    fn config(self) -> ChannelConfig {
+        let send_type = match self {
+            Channel::Movement => SendType::Unreliable,
+            Channel::Events => SendType::ReliableUnordered {
+                resend_time: Duration::from_millis(300),
+            },
+            Channel::Lobby | Channel::Actions => SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            },
+        };
+        ChannelConfig {
+            channel_id: self.into(),
+            max_memory_usage_bytes: 5 * 1024 * 1024,
+            send_type,
+        }
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(channel: Channel) -> Self {
+        match channel {
+            Channel::Movement => 0,
+            Channel::Events => 1,
+            Channel::Lobby => 2,
+            Channel::Actions => 3,
+        }
+    }
+}
+
+/// Doit etre identique cote client et cote serveur
+pub fn connection_config() -> ConnectionConfig {
+    let channels: Vec<ChannelConfig> = [
+        Channel::Movement,
+        Channel::Events,
+        Channel::Lobby,
+        Channel::Actions,
+    ]
+    .into_iter()
+    .map(Channel::config)
+    .collect();
+    ConnectionConfig {
+        available_bytes_per_tick: 60_000,
+        server_channels_config: channels.clone(),
+        client_channels_config: channels,
+    }
+}
+
+/// Nombre de messages recus qui n'ont pas pu etre decodes
+#[derive(Debug, Default, Resource)]
+pub struct DecodeErrors {
+    pub count: u64,
+}
+
+pub fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    options()
+        .serialize(message)
+        .expect("Messages are always smaller than MAX_MESSAGE_SIZE")
+}
+
+/// Ne panique jamais, quel que soit le contenu du paquet
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    if bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(DecodeError::TooLarge(bytes.len()));
+    }
+    options().deserialize(bytes).map_err(DecodeError::Malformed)
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use renet::ClientId;
+use serde::{Deserialize, Serialize};
+
+use crate::keybind::KeyBinds;
+use crate::player::PlayerStats;
+
+/// Une ligne du tableau des scores, envoyee par le serveur dans `ServerMessages::Scoreboard`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreboardEntry {
+    pub id: ClientId,
+    pub name: String,
+    pub stats: PlayerStats,
+    // Aller-retour mesure par renet cote serveur, en millisecondes
+    pub ping: u32,
+}
+
+/// Meilleur score en premier, puis le plus de kills, puis l'id pour un ordre stable
+pub fn sort_entries(entries: &mut [ScoreboardEntry]) {
+    entries.sort_by(|a, b| {
+        b.stats
+            .score()
+            .cmp(&a.stats.score())
+            .then(b.stats.kills.cmp(&a.stats.kills))
+            .then(a.id.raw().cmp(&b.id.raw()))
+    });
+}
+
+/// Dernier tableau recu du serveur, deja trie
+#[derive(Debug, Default, Resource)]
+pub struct Scoreboard {
+    pub entries: Vec<ScoreboardEntry>,
+}
+
+#[derive(Debug, Component)]
+pub struct ScoreboardMenu;
+
+#[derive(Debug, Component)]
+pub struct ScoreboardRows;
+
+const COLUMNS: [(&str, f32); 6] = [
+    ("Name", 260.0),
+    ("Kills", 90.0),
+    ("Deaths", 90.0),
+    ("K/D", 90.0),
+    ("Score", 90.0),
+    ("Ping", 90.0),
+];
+
+fn row(parent: &mut ChildBuilder, values: [String; 6], color: Color) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                margin: UiRect::vertical(Val::Px(2.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            for (value, (_, width)) in values.into_iter().zip(COLUMNS) {
+                row.spawn(
+                    TextBundle::from_section(
+                        value,
+                        TextStyle {
+                            font_size: 24.0,
+                            color,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        width: Val::Px(width),
+                        ..default()
+                    }),
+                );
+            }
+        });
+}
+
+/// Cache jusqu'a ce que la touche du tableau soit enfoncee
+pub fn spawn_scoreboard(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ScoreboardMenu,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                    ..default()
+                })
+                .with_children(|table| {
+                    row(table, COLUMNS.map(|(title, _)| title.to_string()), Color::srgb(1.0, 0.8, 0.2));
+                    table.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ScoreboardRows,
+                    ));
+                });
+        });
+}
+
+/// Affiche le tableau tant que la touche est tenue, refait les lignes a chaque nouveau tableau
+pub fn update_scoreboard(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_binds: Res<KeyBinds>,
+    scoreboard: Res<Scoreboard>,
+    mut menu: Query<&mut Visibility, With<ScoreboardMenu>>,
+    rows: Query<Entity, With<ScoreboardRows>>,
+) {
+    let (Ok(mut visibility), Ok(rows)) = (menu.get_single_mut(), rows.get_single()) else {
+        return;
+    };
+    *visibility = if keyboard_input.pressed(key_binds.scoreboard) {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+    if !scoreboard.is_changed() {
+        return;
+    }
+    commands.entity(rows).despawn_descendants().with_children(|rows| {
+        for entry in &scoreboard.entries {
+            row(
+                rows,
+                [
+                    entry.name.clone(),
+                    entry.stats.kills.to_string(),
+                    entry.stats.deaths.to_string(),
+                    format!("{:.2}", entry.stats.kd_ratio()),
+                    entry.stats.score().to_string(),
+                    entry.ping.to_string(),
+                ],
+                Color::WHITE,
+            );
+        }
+    });
+}
@@ -1,124 +1,571 @@
-use std::{collections::HashMap, net::UdpSocket, time::SystemTime};
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, TcpStream, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use bevy::{
-    app::{App, Plugin, Update},
+    app::{App, AppExit, FixedUpdate, Plugin, ScheduleRunnerPlugin, Startup, Update},
+    asset::{AssetApp, AssetPlugin},
+    hierarchy::HierarchyPlugin,
     math::Vec3,
     prelude::{
-        Commands, Component, EventReader, ResMut, Resource, Transform,
+        Commands, Component, Entity, EventReader, EventWriter, IntoSystemConfigs, Local, Mesh,
+        PluginGroup, Query, Res, ResMut, Resource, Transform, TransformBundle,
     },
+    scene::ScenePlugin,
+    time::{Fixed, Time},
+    transform::TransformPlugin,
+    MinimalPlugins,
+};
+use bevy_rapier3d::prelude::{
+    Collider, ColliderDisabled, KinematicCharacterController, NoUserData, QueryFilter, RapierContext,
+    RapierPhysicsPlugin,
 };
+use bevy_renet::{transport::NetcodeServerPlugin, RenetServerPlugin};
 use renet::{
     transport::{NetcodeServerTransport, ServerAuthentication, ServerConfig},
-    ClientId, ConnectionConfig, DefaultChannel, RenetServer, ServerEvent,
+    ClientId, RenetServer, ServerEvent,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{player::PlayerData, test::HostState};
+use crate::{
+    ammo::Ammo,
+    auth,
+    client::{ClientMessages, MAX_INPUTS_PER_MESSAGE},
+    connection::{ConnectionSettings, PROTOCOL_ID},
+    health::{self, Armor, DamageEvent, Health, HitLocation},
+    hitbox::{self, Flight, FlyingProjectile, HitboxHistory, Hitboxes, MAX_REWIND_SECONDS},
+    inventory::LOADOUT,
+    map::{self, SpawnPoints},
+    player::{
+        player_collider, player_controller, simulate_movement, Affiliation, InputAck,
+        MovementState, PlayerData, PlayerInput, INPUT_DT,
+    },
+    protocol::{self, Channel, DecodeErrors},
+    scoreboard::{self, ScoreboardEntry},
+    snapshot::{self, PlayerDelta, QuantizedPlayer, SnapshotHistory, WorldState},
+    test::HostState,
+    username,
+    projectile::ProjectileId,
+    weapon::WeaponId,
+    weapon_definition::{FireMode, WeaponDefinition, WeaponDefinitions, WeaponRegistry},
+};
+
+// Joueurs par escouade dans une partie par equipes
+pub const SQUAD_SIZE: usize = 4;
 
 #[derive(Debug, Default, Resource)]
 pub struct Lobby {
     pub players: HashMap<ClientId, PlayerData>,
+    // Nombre d'equipes, 0 pour chacun pour soi
+    pub teams: u8,
+}
+
+impl Lobby {
+    /// Equipe et escouade d'un nouveau joueur : l'equipe la moins nombreuse (la premiere a egalite),
+    /// puis la premiere escouade de cette equipe qui n'est pas pleine
+    pub fn assign_affiliation(&self) -> Affiliation {
+        if self.teams == 0 {
+            return Affiliation::default();
+        }
+        let members = |team: u8| {
+            self.players
+                .values()
+                .map(|player| player.affiliation)
+                .filter(move |affiliation| affiliation.team == Some(team))
+        };
+        let team = (0..self.teams)
+            .min_by_key(|team| members(*team).count())
+            .unwrap_or(0);
+        let squad = (0..=u8::MAX).find(|squad| {
+            members(team)
+                .filter(|affiliation| affiliation.squad == Some(*squad))
+                .count()
+                < SQUAD_SIZE
+        });
+        Affiliation {
+            team: Some(team),
+            squad,
+        }
+    }
+
+    /// Position des ennemis en vie de `id`, de l'equipe `team`, evitee au moment de choisir
+    /// un point de depart. Sans equipe, tous les autres joueurs sont des ennemis.
+    pub fn enemy_positions(&self, id: ClientId, team: Option<u8>) -> Vec<Vec3> {
+        self.players
+            .values()
+            .filter(|player| player.id != id && !player.is_dead())
+            .filter(|player| team.is_none() || player.affiliation.team != team)
+            .map(|player| player.position.translation)
+            .collect()
+    }
+
+    /// Compte la mort de `victim` : un kill pour `killer` (sauf suicide), un assist
+    /// pour chaque autre joueur qui l'a blesse depuis son respawn
+    pub fn credit_kill(&mut self, killer: ClientId, victim: ClientId) {
+        let Some(victim_data) = self.players.get_mut(&victim) else {
+            return;
+        };
+        victim_data.stats.deaths += 1;
+        let damaged_by = std::mem::take(&mut victim_data.damaged_by);
+        if killer != victim {
+            if let Some(killer_data) = self.players.get_mut(&killer) {
+                killer_data.stats.kills += 1;
+            }
+        }
+        for id in damaged_by.into_iter().filter(|id| *id != killer && *id != victim) {
+            if let Some(assister) = self.players.get_mut(&id) {
+                assister.stats.assists += 1;
+            }
+        }
+    }
+
+    /// Tableau des scores de tous les joueurs connectes, trie pour l'affichage
+    pub fn scoreboard(&self) -> Vec<ScoreboardEntry> {
+        let mut entries: Vec<ScoreboardEntry> = self
+            .players
+            .values()
+            .map(|player| ScoreboardEntry {
+                id: player.id,
+                name: player.name.clone(),
+                stats: player.stats,
+                ping: player.ping,
+            })
+            .collect();
+        scoreboard::sort_entries(&mut entries);
+        entries
+    }
+}
+
+/// Numero du dernier tick simule, envoye dans chaque `WorldSnapshot`
+#[derive(Debug, Resource)]
+pub struct ServerTick {
+    pub rate: u32,
+    pub tick: u32,
+}
+
+/// Taille des snapshots envoyes, affichee regulierement dans la console du serveur
+#[derive(Debug, Default, Resource)]
+pub struct SnapshotStats {
+    pub ticks: u32,
+    pub delta_bytes: usize,
+    // Ce qu'auraient coute des snapshots complets sur les memes ticks.
+    // Compte seulement en debug : il faut encoder un second snapshot par client
+    pub full_bytes: usize,
+}
+
+/// Tir recu d'un client, verifie au prochain tick
+#[derive(Debug, Clone, Copy)]
+pub struct Shot {
+    pub projectile_id: ProjectileId,
+    pub shooter: ClientId,
+    pub weapon: WeaponId,
+    pub origin: Vec3,
+    pub direction: Vec3,
+    // Heure serveur des joueurs distants affiches chez le tireur au moment du tir
+    pub view_time: f64,
+    // Tir instantane : joueur que le tireur dit avoir touche, `None` pour un projectile
+    pub claim: Option<ClientId>,
+}
+
+/// Projectile en vol cote serveur : c'est lui, pas la balle du client, qui decide des touches
+#[derive(Debug, Clone, Copy)]
+pub struct ServerProjectile {
+    pub projectile_id: ProjectileId,
+    pub weapon: WeaponId,
+    pub flight: FlyingProjectile,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct ServerProjectiles {
+    pub projectiles: Vec<ServerProjectile>,
+}
+
+#[derive(Debug, Default, Resource)]
+pub struct PendingShots {
+    pub shots: Vec<Shot>,
+    // Id du prochain projectile, jamais reutilise pendant la partie
+    pub next_projectile_id: ProjectileId,
 }
 
-pub struct Server;
+pub struct Server {
+    // Ticks par seconde : simulation des commandes recues puis envoi d'un WorldSnapshot
+    pub tick_rate: u32,
+    // Nombre d'equipes, 0 pour chacun pour soi
+    pub teams: u8,
+}
 
 impl Plugin for Server {
     fn build(&self, app: &mut App) {
         // app.init_state::<HostState>();
-        let server = RenetServer::new(ConnectionConfig::default());
+        let server = RenetServer::new(protocol::connection_config());
         // let (server, server_transport) = new_renet_server();
         app.insert_resource(server);
         // app.insert_resource(server_transport);
-        app.init_resource::<Lobby>();
+        app.insert_resource(Lobby {
+            teams: self.teams,
+            ..Default::default()
+        });
+        app.init_resource::<DecodeErrors>();
         app.init_resource::<HostState>();
-        app.add_systems(Update, (server_update_system, server_centralize_messages));
+        app.insert_resource(Time::<Fixed>::from_hz(self.tick_rate as f64));
+        app.insert_resource(ServerTick {
+            rate: self.tick_rate,
+            tick: 0,
+        });
+        app.init_resource::<SnapshotHistory>();
+        app.init_resource::<SnapshotStats>();
+        app.init_resource::<HitboxHistory>();
+        app.init_resource::<PendingShots>();
+        app.init_resource::<ServerProjectiles>();
+        app.add_event::<DamageEvent>();
+        app.add_plugins(WeaponDefinitions);
+        app.add_systems(Startup, map::spawn_map_colliders);
+        // Les messages sont accumules entre deux ticks
+        app.init_resource::<ShutdownSignal>();
+        app.add_systems(
+            Update,
+            (
+                server_update_system,
+                server_centralize_messages,
+                server_stop_on_shutdown,
+            ),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                server_respawn_players,
+                server_finish_reloads,
+                server_simulate_players,
+                server_send_snapshots,
+                server_record_hitboxes,
+                server_resolve_shots,
+                server_update_projectiles,
+                server_apply_damage,
+                server_send_scoreboard,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Joueur simule par le serveur, c'est sa position qui fait foi
+#[derive(Debug, Component)]
+pub struct ServerPlayer {
+    pub id: ClientId,
+}
+
+// Frequence de la boucle principale du serveur (pas de fenetre pour la cadencer)
+const SERVER_FRAME_TIME: Duration = Duration::from_millis(1000 / 60);
+// Au dela, les commandes en retard sont jetees pour qu'un client ne puisse pas accelerer
+const MAX_PENDING_INPUTS: usize = 8;
+// Commandes jouees en plus par tick pour rattraper un retard, sans laisser un client jouer plus vite que le serveur
+const CATCH_UP_INPUTS_PER_TICK: usize = 1;
+const STATS_INTERVAL_SECONDS: u32 = 5;
+// Un tir part de l'arme du tireur : sa position predite peut avoir un peu d'avance sur celle du serveur
+const MAX_SHOT_ORIGIN_DISTANCE: f32 = 3.0;
+/// Portee des tirs, aussi celle du rayon d'un tir instantane chez le client
+pub const MAX_SHOT_DISTANCE: f32 = 200.0;
+const RESPAWN_DELAY_SECONDS: f32 = 3.0;
+const PING_INTERVAL_SECONDS: u32 = 1;
+// Avance laissee au client sur la fin d'un rechargement ou d'un changement d'arme, le temps que son tir arrive
+const CLIENT_LEAD_SECONDS: f32 = 0.25;
+
+/// App sans fenetre ni rendu qui fait tourner le serveur avec sa propre physique.
+/// Utilisee par le binaire `dedicated_server` et par le bouton "Create" du client.
+pub fn new_server_app(server_transport: NetcodeServerTransport, tick_rate: u32, teams: u8) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(SERVER_FRAME_TIME)))
+        .add_plugins((
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            ScenePlugin,
+        ))
+        // Rapier attend les assets de mesh meme sans rendu
+        .init_asset::<Mesh>()
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugins(Server { tick_rate, teams })
+        .add_plugins(RenetServerPlugin)
+        .add_plugins(NetcodeServerPlugin)
+        // Le serveur est toujours l'hote, pas besoin d'attendre le bouton "Create"
+        .insert_resource(HostState {
+            is_host: true,
+            is_host_initialized: true,
+        })
+        .insert_resource(server_transport);
+    app
+}
+
+/// Arret demande depuis un autre thread au serveur heberge par le client
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    pub fn request(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
     }
 }
 
+/// Serveur lance par le bouton "Create", arrete si le client n'arrive pas a s'y connecter
+pub struct ListenServer {
+    shutdown: ShutdownSignal,
+    // Serveur de tokens integre, bloque en attente d'un client
+    token_server: Option<SocketAddr>,
+}
+
+impl ListenServer {
+    /// Libere les ports pour pouvoir reessayer
+    pub fn shutdown(self) {
+        self.shutdown.request();
+        if let Some(addr) = self.token_server {
+            let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+        }
+    }
+}
+
+/// Heberge une partie depuis le client : le serveur tourne dans son propre thread et son propre monde
+pub fn spawn_listen_server(settings: &ConnectionSettings) -> io::Result<ListenServer> {
+    let shutdown = ShutdownSignal::default();
+    let server_transport = new_renet_server(settings, &shutdown)?;
+    let (tick_rate, teams) = (settings.tick_rate, settings.teams);
+    let app_shutdown = shutdown.clone();
+    std::thread::spawn(move || {
+        let mut app = new_server_app(server_transport, tick_rate, teams);
+        app.insert_resource(app_shutdown).run();
+    });
+    let token_server = (settings.secure && settings.token_server_address.is_none())
+        .then(|| settings.local_server_addr());
+    Ok(ListenServer {
+        shutdown,
+        token_server,
+    })
+}
+
+/// Quitte la boucle du serveur, ce qui ferme son socket
+fn server_stop_on_shutdown(shutdown: Res<ShutdownSignal>, mut exit: EventWriter<AppExit>) {
+    if shutdown.is_requested() {
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Messages envoyes par le serveur. Les `id` sont toujours ceux que le serveur a attribues.
 #[derive(Debug, Serialize, Deserialize, Component)]
 pub enum ServerMessages {
-    PlayerConnected { id: ClientId, position: Vec3 },
+    // `name` : pseudo retenu par le serveur, pas forcement celui demande
+    PlayerConnected { id: ClientId, name: String, position: Vec3, affiliation: Affiliation },
     PlayerDisconnected { id: ClientId },
-    PlayerMoved { id: ClientId, position: Vec3 },
-    ProjectileSpawned { id: ClientId, position: Vec3, direction: Vec3  },
+    // Frequence des ticks, envoyee a la connexion pour dater les snapshots
+    ServerInfo { tick_rate: u32 },
+    // Un seul par tick et par client : l'etat exact du joueur qui le recoit apres sa derniere
+    // commande simulee, et les autres joueurs du Lobby en delta par rapport au tick `baseline`
+    // (0 pour un snapshot complet)
+    WorldSnapshot {
+        tick: u32,
+        baseline: u32,
+        ack: InputAck,
+        removed: Vec<ClientId>,
+        players: Vec<PlayerDelta>,
+    },
+    // `shot` est le numero du tir chez `owner`, qui retrouve ainsi son projectile
+    ProjectileSpawned {
+        projectile_id: ProjectileId,
+        owner: ClientId,
+        shot: u32,
+        weapon: WeaponId,
+        position: Vec3,
+        direction: Vec3,
+    },
     TestMessage { message: String },
-    PlayerDeath { id: ClientId }
+    // Le joueur reapparait dans `respawn_delay` secondes. `weapon` et `headshot` viennent du
+    // coup fatal, pour le kill feed
+    PlayerDeath {
+        killer: ClientId,
+        victim: ClientId,
+        weapon: WeaponId,
+        headshot: bool,
+        respawn_delay: f32,
+    },
+    // Vie, armure et mouvement remis a zero, au point de depart choisi par le serveur
+    PlayerRespawned { id: ClientId, position: Vec3 },
     // ProjectileMoved { id: ClientId, position: Vec3},
+    // Touche confirmee par le serveur, `position` est le point d'impact
+    PlayerHit {
+        projectile_id: ProjectileId,
+        shooter: ClientId,
+        victim: ClientId,
+        position: Vec3,
+    },
+    // Nouvelle vie et armure d'un joueur, envoyee a tout le monde
+    HealthChanged { id: ClientId, health: Health, armor: Armor },
+    // Tableau complet, renvoye seulement quand une stat ou un ping change
+    Scoreboard { players: Vec<ScoreboardEntry> },
+    // Munitions qui font foi, envoyees au seul proprietaire apres un rechargement ou un tir refuse
+    AmmoChanged { weapon: WeaponId, ammo: Ammo },
+    // Tir instantane d'un autre joueur, seulement pour afficher sa trainee
+    HitscanFired {
+        owner: ClientId,
+        weapon: WeaponId,
+        origin: Vec3,
+        end: Vec3,
+    },
+}
+
+impl ServerMessages {
+    pub fn channel(&self) -> Channel {
+        match self {
+            ServerMessages::WorldSnapshot { .. } => Channel::Movement,
+            ServerMessages::ProjectileSpawned { .. }
+            | ServerMessages::PlayerDeath { .. }
+            | ServerMessages::PlayerHit { .. }
+            | ServerMessages::HitscanFired { .. } => Channel::Events,
+            ServerMessages::ServerInfo { .. }
+            | ServerMessages::PlayerConnected { .. }
+            | ServerMessages::PlayerDisconnected { .. }
+            | ServerMessages::TestMessage { .. }
+            | ServerMessages::Scoreboard { .. }
+            | ServerMessages::AmmoChanged { .. } => Channel::Lobby,
+            // Ordonne : la derniere valeur recue est toujours la bonne
+            ServerMessages::HealthChanged { .. } | ServerMessages::PlayerRespawned { .. } => {
+                Channel::Lobby
+            }
+        }
+    }
 }
 
-const PROTOCOL_ID: u64 = 7;
 // use crate::player::Player;
 
-pub fn new_renet_server() -> NetcodeServerTransport {
+pub fn new_renet_server(
+    settings: &ConnectionSettings,
+    shutdown: &ShutdownSignal,
+) -> io::Result<NetcodeServerTransport> {
     println!("Creating server");
-    // let public_addr = "192.168.101.234:5000".parse().unwrap();
-    let public_addr = (local_ip_address::local_ip().unwrap().to_string() + ":5000").parse().unwrap();
-    let socket = UdpSocket::bind(public_addr).unwrap();
+    let socket = UdpSocket::bind(settings.server_bind_addr())?;
     let current_time = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
+    let authentication = if settings.secure {
+        let private_key = match (settings.private_key, &settings.token_server_address) {
+            (Some(private_key), _) => private_key,
+            (None, None) => auth::random_private_key(),
+            // Un serveur de tokens externe doit connaitre la meme cle
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--token-server demande aussi --key",
+                ))
+            }
+        };
+        if settings.token_server_address.is_none() {
+            auth::spawn_token_server(
+                settings.server_bind_addr(),
+                private_key,
+                settings.require_password()?,
+                settings.server_public_addrs(),
+                shutdown.clone(),
+            )?;
+        }
+        ServerAuthentication::Secure { private_key }
+    } else {
+        ServerAuthentication::Unsecure
+    };
     let server_config = ServerConfig {
         current_time,
         max_clients: 64,
         protocol_id: PROTOCOL_ID,
-        public_addresses: vec![public_addr],
-        authentication: ServerAuthentication::Unsecure,
+        public_addresses: settings.server_public_addrs(),
+        authentication,
     };
 
-    let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
-    // let server = RenetServer::new(ConnectionConfig::default());
-
-    transport
+    NetcodeServerTransport::new(server_config, socket)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn server_update_system(
     mut server_events: EventReader<ServerEvent>,
     mut commands: Commands,
     mut lobby: ResMut<Lobby>,
     mut server: ResMut<RenetServer>,
-    mut host_state: ResMut<HostState>,
+    server_tick: Res<ServerTick>,
+    spawn_points: Res<SpawnPoints>,
+    transport: Res<NetcodeServerTransport>,
+    healths: Query<(&ServerPlayer, &Health, &Armor)>,
 ) {
-    if host_state.is_host && !host_state.is_host_initialized {
-        let server_transport = new_renet_server();
-        commands.insert_resource(server_transport);
-        host_state.is_host_initialized = true;
-    } else {
-        // Handle server events
-        for event in server_events.read() {
-            match event {
-                ServerEvent::ClientConnected { client_id} => {
-                    // println!("Server side : Player {} connected.", client_id);
-                    // Envoie les données des joueurs connectés au nouveau joueur
-                    lobby
-                        .players
-                        .insert(*client_id, PlayerData::new(*client_id));
-                    for &player_id in lobby.players.keys() {
-                        let message =
-                            bincode::serialize(&ServerMessages::PlayerConnected { id: player_id, position: lobby.players[&player_id].position.translation })
-                                .unwrap();
-                        server.send_message(*client_id, DefaultChannel::ReliableOrdered, message);
-                    }
-
-                    // Envoie la nouvelle connexion aux joueurs déjà connectés
-                    let message =
-                        bincode::serialize(&ServerMessages::PlayerConnected { id: *client_id, position: lobby.players[client_id].position.translation })
-                            .unwrap();
-                    server.broadcast_message(DefaultChannel::ReliableOrdered, message);
+    // Handle server events
+    for event in server_events.read() {
+        match event {
+            ServerEvent::ClientConnected { client_id} => {
+                // println!("Server side : Player {} connected.", client_id);
+                let mut player_data = PlayerData::new(*client_id);
+                let requested = transport
+                    .user_data(*client_id)
+                    .and_then(|user_data| username::from_user_data(&user_data))
+                    .unwrap_or_default();
+                let name = username::validate(&requested).unwrap_or_else(|error| {
+                    println!("Server side : Player {} : {}", client_id, error);
+                    username::DEFAULT_USERNAME.to_string()
+                });
+                player_data.name = username::make_unique(
+                    &name,
+                    lobby.players.values().map(|player| player.name.as_str()),
+                );
+                player_data.affiliation = lobby.assign_affiliation();
+                println!("Server side : Player {} connected as {}", client_id, player_data.name);
+                let enemies = lobby.enemy_positions(*client_id, player_data.affiliation.team);
+                player_data.position.translation = spawn_points.choose(&enemies);
+                let player_entity = commands
+                    .spawn((
+                        ServerPlayer { id: *client_id },
+                        TransformBundle::from_transform(player_data.position),
+                        player_collider(),
+                        player_controller(),
+                        MovementState::default(),
+                        Health::default(),
+                        Armor::default(),
+                    ))
+                    .id();
+                player_data.entity = Some(player_entity);
+                let message = ServerMessages::ServerInfo { tick_rate: server_tick.rate };
+                server.send_message(*client_id, message.channel(), protocol::encode(&message));
+                // Envoie les données des joueurs connectés au nouveau joueur
+                lobby.players.insert(*client_id, player_data);
+                for player in lobby.players.values() {
+                    let message = ServerMessages::PlayerConnected { id: player.id, name: player.name.clone(), position: player.position.translation, affiliation: player.affiliation };
+                    server.send_message(*client_id, message.channel(), protocol::encode(&message));
                 }
-                ServerEvent::ClientDisconnected { client_id, reason } => {
-                    println!(
-                        "Server side : Player {} disconnected: {}",
-                        client_id, reason
-                    );
-                    // if let Some(player_entity) = lobby.players.remove(client_id) {
-                    //     commands.entity(player_entity).despawn();
-                    // }
-                    lobby.players.remove(client_id);
-                    let message =
-                        bincode::serialize(&ServerMessages::PlayerDisconnected { id: *client_id })
-                            .unwrap();
-                    server.broadcast_message(DefaultChannel::ReliableOrdered, message);
+                // Les joueurs deja la ont peut-etre perdu de la vie
+                for (player, health, armor) in healths.iter() {
+                    let message = ServerMessages::HealthChanged { id: player.id, health: *health, armor: *armor };
+                    server.send_message(*client_id, message.channel(), protocol::encode(&message));
                 }
+
+                // Envoie la nouvelle connexion aux joueurs déjà connectés
+                let player = &lobby.players[client_id];
+                let message = ServerMessages::PlayerConnected { id: *client_id, name: player.name.clone(), position: player.position.translation, affiliation: player.affiliation };
+                server.broadcast_message(message.channel(), protocol::encode(&message));
+            }
+            ServerEvent::ClientDisconnected { client_id, reason } => {
+                println!(
+                    "Server side : Player {} disconnected: {}",
+                    client_id, reason
+                );
+                if let Some(player_entity) =
+                    lobby.players.remove(client_id).and_then(|player| player.entity)
+                {
+                    commands.entity(player_entity).despawn();
+                }
+                let message = ServerMessages::PlayerDisconnected { id: *client_id };
+                server.broadcast_message(message.channel(), protocol::encode(&message));
             }
         }
     }
@@ -128,47 +575,739 @@ pub fn server_centralize_messages(
     mut server: ResMut<RenetServer>,
     mut lobby: ResMut<Lobby>,
     host_state: ResMut<HostState>,
+    mut decode_errors: ResMut<DecodeErrors>,
+    mut pending_shots: ResMut<PendingShots>,
+    weapons: Res<WeaponRegistry>,
+    server_tick: Res<ServerTick>,
 ) {
     if !host_state.is_host {
         return;
     }
     for client_id in server.clients_id() {
         // println!("Checking messages for client {}", client_id);
-        while let Some(message) = server.receive_message(client_id, DefaultChannel::ReliableOrdered)
+        while let Some(message) = Channel::ALL
+            .into_iter()
+            .find_map(|channel| server.receive_message(client_id, channel))
         {
-            let server_message: ServerMessages = bincode::deserialize(&message).unwrap();
-            match server_message {
-                ServerMessages::PlayerMoved { id, position } => {
-                    if let Some(player) = lobby.players.get_mut(&id) {
-                        player.position = Transform::from_xyz(position.x, position.y, position.z);
-                        let message =
-                            bincode::serialize(&ServerMessages::PlayerMoved { id, position })
-                                .unwrap();
-                        server.broadcast_message(DefaultChannel::ReliableOrdered, message);
-                        // println!("Player {} moved to {:?}", id, position);
+            let client_message: ClientMessages = match protocol::decode(&message) {
+                Ok(client_message) => client_message,
+                Err(error) => {
+                    // Client modifie ou d'une autre version : on ne lit pas la suite
+                    decode_errors.count += 1;
+                    println!("Disconnecting client {}: {}", client_id, error);
+                    server.disconnect(client_id);
+                    break;
+                }
+            };
+            // L'expediteur est toujours client_id, le canal sur lequel le message est arrive
+            match client_message {
+                ClientMessages::PlayerInput { sequence, inputs } => {
+                    // Le serveur ne fait plus confiance aux positions envoyees par les clients,
+                    // il garde les commandes et les simule dans server_simulate_players
+                    if let Some(player) = lobby.players.get_mut(&client_id) {
+                        receive_inputs(player, sequence, inputs);
                     }
                 }
-                ServerMessages::TestMessage { message } => {
+                ClientMessages::TestMessage { message } => {
                     println!("Message test from client {}: {}", client_id, message);
                 }
-                ServerMessages::ProjectileSpawned { id, position, direction } => {
+                ClientMessages::SnapshotAck { tick } => {
+                    if let Some(player) = lobby.players.get_mut(&client_id) {
+                        player.snapshot_ack = player.snapshot_ack.max(tick);
+                    }
+                }
+                ClientMessages::ProjectileSpawned {
+                    shot,
+                    weapon,
+                    position,
+                    direction,
+                    view_time,
+                } => {
                     // println!(
                     //     "Server side : projectiles id :  {} position: {}",
                     //     client_id, position
                     // );
-                    let message =
-                        bincode::serialize(&ServerMessages::ProjectileSpawned { id, position, direction })
-                            .unwrap();
-                    server.broadcast_message(DefaultChannel::ReliableOrdered, message);
+                    // Un mort ou une arme inconnue ne tire pas
+                    let (Some(player), Some(definition)) =
+                        (lobby.players.get_mut(&client_id), weapons.get(weapon))
+                    else {
+                        continue;
+                    };
+                    if player.is_dead() || definition.fire_mode != FireMode::Projectile {
+                        continue;
+                    }
+                    // Verifie avant d'etre renvoye aux autres clients
+                    let Some(direction) = shot_direction(player, position, direction) else {
+                        continue;
+                    };
+                    if let Err(ammo) = take_shot(player, definition, &server_tick) {
+                        // Le client s'est trompe de compte : on lui renvoie le bon
+                        let message = ServerMessages::AmmoChanged { weapon, ammo };
+                        server.send_message(client_id, message.channel(), protocol::encode(&message));
+                        continue;
+                    }
+                    pending_shots.next_projectile_id += 1;
+                    let projectile_id = pending_shots.next_projectile_id;
+                    pending_shots.shots.push(Shot {
+                        projectile_id,
+                        shooter: client_id,
+                        weapon,
+                        origin: position,
+                        direction,
+                        view_time,
+                        claim: None,
+                    });
+                    let message = ServerMessages::ProjectileSpawned {
+                        projectile_id,
+                        owner: client_id,
+                        shot,
+                        weapon,
+                        position,
+                        direction,
+                    };
+                    server.broadcast_message(message.channel(), protocol::encode(&message));
                 }
-                ServerMessages::PlayerDeath { id } => {
-                    let message =
-                        bincode::serialize(&ServerMessages::PlayerDeath { id })
-                            .unwrap();
-                    server.broadcast_message(DefaultChannel::ReliableOrdered, message);
+                ClientMessages::HitscanFired {
+                    weapon,
+                    origin,
+                    direction,
+                    distance,
+                    view_time,
+                    victim,
+                } => {
+                    let (Some(player), Some(definition)) =
+                        (lobby.players.get_mut(&client_id), weapons.get(weapon))
+                    else {
+                        continue;
+                    };
+                    if player.is_dead() || definition.fire_mode != FireMode::Hitscan {
+                        continue;
+                    }
+                    // La trainee part de `origin` chez les autres clients
+                    let Some(direction) = shot_direction(player, origin, direction) else {
+                        continue;
+                    };
+                    if let Err(ammo) = take_shot(player, definition, &server_tick) {
+                        let message = ServerMessages::AmmoChanged { weapon, ammo };
+                        server.send_message(client_id, message.channel(), protocol::encode(&message));
+                        continue;
+                    }
+                    // Un tir manque n'a rien a verifier
+                    if victim.is_some() {
+                        pending_shots.next_projectile_id += 1;
+                        let projectile_id = pending_shots.next_projectile_id;
+                        pending_shots.shots.push(Shot {
+                            projectile_id,
+                            shooter: client_id,
+                            weapon,
+                            origin,
+                            direction,
+                            view_time,
+                            claim: victim,
+                        });
+                    }
+                    // La trainee est purement visuelle, on la borne juste a la portee
+                    let distance = if distance.is_finite() {
+                        distance.clamp(0.0, MAX_SHOT_DISTANCE)
+                    } else {
+                        0.0
+                    };
+                    let message = ServerMessages::HitscanFired {
+                        owner: client_id,
+                        weapon,
+                        origin,
+                        end: origin + direction * distance,
+                    };
+                    server.broadcast_message_except(
+                        client_id,
+                        message.channel(),
+                        protocol::encode(&message),
+                    );
+                }
+                ClientMessages::Reload { weapon } => {
+                    let (Some(player), Some(definition)) =
+                        (lobby.players.get_mut(&client_id), weapons.get(weapon))
+                    else {
+                        continue;
+                    };
+                    let ammo = player
+                        .ammo
+                        .get(&weapon)
+                        .copied()
+                        .unwrap_or_else(|| Ammo::full(definition));
+                    if player.is_dead()
+                        || player.reload.is_some()
+                        || weapon != player.weapon
+                        || !ammo.can_reload(definition)
+                    {
+                        continue;
+                    }
+                    let ticks = (definition.reload_time * server_tick.rate as f32).ceil() as u32;
+                    player.reload = Some((weapon, server_tick.tick + ticks));
+                }
+                ClientMessages::SwitchWeapon { weapon } => {
+                    let (Some(player), Some(definition)) =
+                        (lobby.players.get_mut(&client_id), weapons.get(weapon))
+                    else {
+                        continue;
+                    };
+                    // Une arme hors du loadout ne peut pas etre sortie, donc pas tirer non plus
+                    if weapon == player.weapon || !LOADOUT.contains(&weapon) {
+                        continue;
+                    }
+                    player.weapon = weapon;
+                    player.reload = None;
+                    player.weapon_ready_tick = server_tick.tick
+                        + (definition.equip_time * server_tick.rate as f32).ceil() as u32;
                 }
-                _ => {}
             }
         }
     }
 }
+
+/// Direction unitaire d'un tir, `None` si `player` ne peut pas l'avoir tire : origine invalide
+/// ou trop loin de lui, direction nulle ou invalide
+fn shot_direction(player: &PlayerData, origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    let direction = direction.normalize_or_zero();
+    let plausible = origin.is_finite()
+        && origin.distance(player.position.translation) <= MAX_SHOT_ORIGIN_DISTANCE
+        && direction != Vec3::ZERO;
+    plausible.then_some(direction)
+}
+
+/// Garde les commandes d'un message `PlayerInput` pas encore recues. `sequence` est celle de
+/// la derniere : un message incoherent est ignore en entier.
+pub fn receive_inputs(player: &mut PlayerData, sequence: u32, inputs: Vec<PlayerInput>) {
+    let count = inputs.len() as u32;
+    if count == 0 || inputs.len() > MAX_INPUTS_PER_MESSAGE || count > sequence {
+        return;
+    }
+    // `u32::MAX` ne laisse pas de place a la commande suivante
+    let Some(first) = sequence.checked_add(1).map(|next| next - count) else {
+        return;
+    };
+    // Le paquet repete les commandes deja recues : seules les nouvelles sont gardees
+    for (sequence, input) in (first..=sequence).zip(inputs) {
+        let newest = player
+            .pending_inputs
+            .back()
+            .map_or(player.last_input_sequence, |(sequence, _)| *sequence);
+        // NaN ou infini : la commande est jetee, sinon elle est bornee avant d'etre jouee
+        if let Some(input) = input.sanitized().filter(|_| sequence > newest) {
+            player.pending_inputs.push_back((sequence, input));
+        }
+    }
+}
+
+/// Retire une balle si le joueur peut tirer avec `definition` a ce tick. Sinon renvoie
+/// ses munitions, pour corriger le client.
+pub fn take_shot(
+    player: &mut PlayerData,
+    definition: &WeaponDefinition,
+    server_tick: &ServerTick,
+) -> Result<(), Ammo> {
+    let weapon = definition.id;
+    let ammo = player
+        .ammo
+        .entry(weapon)
+        .or_insert_with(|| Ammo::full(definition));
+    // Le client finit son rechargement sans attendre la latence : on lui laisse un peu d'avance
+    let lead = (CLIENT_LEAD_SECONDS * server_tick.rate as f32) as u32;
+    // Arme pas en main ou pas encore sortie
+    let switching =
+        weapon != player.weapon || server_tick.tick + lead < player.weapon_ready_tick;
+    // Plus vite que la cadence de l'arme. La latence groupe parfois les tirs : l'avance est
+    // toleree au total, pas a chaque tir, sinon elle couvrirait tout un chargeur
+    let interval = (definition.fire_interval * server_tick.rate as f32) as f64;
+    let now = server_tick.tick as f64;
+    let too_fast = player
+        .last_shot_tick
+        .is_some_and(|last| last + interval > now + lead as f64);
+    let mut reloading = false;
+    if let Some((reloaded, tick)) = player.reload {
+        if reloaded == weapon && tick <= server_tick.tick + lead {
+            ammo.reload(definition);
+            player.reload = None;
+        } else {
+            reloading = reloaded == weapon;
+        }
+    }
+    if switching || reloading || too_fast || !ammo.try_fire() {
+        return Err(*ammo);
+    }
+    player.last_shot_tick = Some(
+        player
+            .last_shot_tick
+            .map_or(now, |last| (last + interval).max(now)),
+    );
+    Ok(())
+}
+
+/// Joue les commandes recues depuis le dernier tick, au pas fixe du client
+pub fn server_simulate_players(
+    time: Res<Time>,
+    mut context: ResMut<RapierContext>,
+    mut lobby: ResMut<Lobby>,
+    mut players: Query<(
+        Entity,
+        &ServerPlayer,
+        &mut Transform,
+        &Collider,
+        &KinematicCharacterController,
+        &mut MovementState,
+    )>,
+) {
+    // A 30 Hz, chaque tick joue les deux commandes envoyees par le client entre temps
+    let inputs_per_tick = (time.delta_seconds() / INPUT_DT).round().max(1.0) as usize;
+    for (entity, server_player, mut transform, collider, controller, mut state) in
+        players.iter_mut()
+    {
+        let Some(player) = lobby.players.get_mut(&server_player.id) else {
+            continue;
+        };
+        while player.pending_inputs.len() > MAX_PENDING_INPUTS {
+            player.pending_inputs.pop_front();
+        }
+        // Le joueur n'avance que quand le client a joue une commande, comme sa prediction
+        if player.pending_inputs.is_empty() {
+            continue;
+        }
+        for _ in 0..inputs_per_tick + CATCH_UP_INPUTS_PER_TICK {
+            let Some((sequence, mut input)) = player.pending_inputs.pop_front() else {
+                break;
+            };
+            // Mort : on peut regarder autour de soi mais plus bouger, le client fait pareil
+            if player.is_dead() {
+                input.movement = Vec3::ZERO;
+            }
+            simulate_movement(
+                &mut context,
+                entity,
+                &mut transform,
+                collider,
+                controller,
+                &mut state,
+                &input,
+                INPUT_DT,
+            );
+            transform.rotation = input.rotation();
+            player.last_input_sequence = sequence;
+        }
+        player.position = *transform;
+    }
+}
+
+/// Un `WorldSnapshot` par client et par tick, meme sans mouvement : le debit ne depend
+/// plus de la frequence d'images des clients, et l'interpolation ne prolonge pas un joueur arrete
+pub fn server_send_snapshots(
+    mut server: ResMut<RenetServer>,
+    mut server_tick: ResMut<ServerTick>,
+    mut history: ResMut<SnapshotHistory>,
+    mut stats: ResMut<SnapshotStats>,
+    lobby: Res<Lobby>,
+    players: Query<(&ServerPlayer, &Transform, &MovementState)>,
+) {
+    server_tick.tick += 1;
+    let tick = server_tick.tick;
+    let state: WorldState = lobby
+        .players
+        .values()
+        .map(|player| (player.id, QuantizedPlayer::from_transform(&player.position)))
+        .collect();
+    history.push(tick, state.clone());
+
+    let empty = WorldState::new();
+    for (server_player, transform, movement) in players.iter() {
+        let Some(player) = lobby.players.get(&server_player.id) else {
+            continue;
+        };
+        let ack = InputAck {
+            sequence: player.last_input_sequence,
+            position: transform.translation,
+            movement: *movement,
+        };
+        // Delta par rapport au dernier snapshot que le client a confirme, complet s'il est trop vieux
+        let (baseline, baseline_state) = match history.get(player.snapshot_ack) {
+            Some(baseline_state) => (player.snapshot_ack, baseline_state),
+            None => (0, &empty),
+        };
+        let (removed, players) = snapshot::diff(baseline_state, &state, player.id);
+        let message = ServerMessages::WorldSnapshot {
+            tick,
+            baseline,
+            ack,
+            removed,
+            players,
+        };
+        let bytes = protocol::encode(&message);
+
+        if cfg!(debug_assertions) {
+            let (removed, players) = snapshot::diff(&empty, &state, player.id);
+            stats.full_bytes += protocol::encode(&ServerMessages::WorldSnapshot {
+                tick,
+                baseline: 0,
+                ack,
+                removed,
+                players,
+            })
+            .len();
+        }
+        stats.delta_bytes += bytes.len();
+        server.send_message(server_player.id, message.channel(), bytes);
+    }
+
+    stats.ticks += 1;
+    if stats.ticks == server_tick.rate * STATS_INTERVAL_SECONDS {
+        if !lobby.players.is_empty() {
+            let full = if cfg!(debug_assertions) {
+                format!(
+                    " (full snapshots: {} bytes/tick)",
+                    stats.full_bytes / stats.ticks as usize
+                )
+            } else {
+                String::new()
+            };
+            println!(
+                "Snapshots: {} bytes/tick{} for {} players",
+                stats.delta_bytes / stats.ticks as usize,
+                full,
+                lobby.players.len()
+            );
+        }
+        *stats = SnapshotStats::default();
+    }
+}
+
+/// Garde la position de chaque joueur a ce tick, celle que les clients recoivent dans le snapshot
+pub fn server_record_hitboxes(
+    server_tick: Res<ServerTick>,
+    lobby: Res<Lobby>,
+    mut history: ResMut<HitboxHistory>,
+) {
+    let hitboxes: Hitboxes = lobby
+        .players
+        .values()
+        // Un mort ne peut plus etre touche
+        .filter(|player| !player.is_dead())
+        .map(|player| (player.id, player.position.translation))
+        .collect();
+    history.push(server_tick.tick, hitboxes);
+}
+
+/// Rejoue chaque tir recu avec les joueurs remis la ou le tireur les voyait.
+/// Tir instantane : le premier obstacle du rayon decide, un mur avant le joueur arrete le tir.
+/// Projectile : il est lance a l'instant vu par le tireur, server_update_projectiles le fait voler.
+#[allow(clippy::too_many_arguments)]
+pub fn server_resolve_shots(
+    mut server: ResMut<RenetServer>,
+    mut pending_shots: ResMut<PendingShots>,
+    mut flying: ResMut<ServerProjectiles>,
+    server_tick: Res<ServerTick>,
+    history: Res<HitboxHistory>,
+    lobby: Res<Lobby>,
+    context: Res<RapierContext>,
+    players: Query<&ServerPlayer>,
+    weapons: Res<WeaponRegistry>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let rate = server_tick.rate as f64;
+    let now = server_tick.tick as f64;
+    for shot in pending_shots.shots.drain(..) {
+        let (Some(shooter), Some(definition)) =
+            (lobby.players.get(&shot.shooter), weapons.get(shot.weapon))
+        else {
+            continue;
+        };
+        // Le joueur a pu bouger depuis la reception du tir
+        let Some(direction) = shot_direction(shooter, shot.origin, shot.direction) else {
+            continue;
+        };
+        let view_tick = if shot.view_time.is_finite() {
+            (shot.view_time * rate).clamp(now - MAX_REWIND_SECONDS * rate, now)
+        } else {
+            now
+        };
+        let victim = match (definition.fire_mode, shot.claim) {
+            // Le serveur fait voler sa propre balle, c'est elle qui touchera ou non
+            (FireMode::Projectile, _) => {
+                flying.projectiles.push(ServerProjectile {
+                    projectile_id: shot.projectile_id,
+                    weapon: shot.weapon,
+                    flight: FlyingProjectile {
+                        shooter: shot.shooter,
+                        position: shot.origin,
+                        velocity: direction * definition.projectile_speed,
+                        tick: view_tick,
+                        remaining: definition.projectile_lifetime,
+                    },
+                });
+                continue;
+            }
+            // Le tireur dit qui il a touche, verifie a l'instant qu'il voyait
+            (FireMode::Hitscan, Some(victim)) => victim,
+            (FireMode::Hitscan, None) => continue,
+        };
+        let hitboxes = history.rewind(view_tick);
+        let Some(distance) = hitbox::confirm_claim(
+            &hitboxes,
+            shot.shooter,
+            victim,
+            shot.origin,
+            direction,
+            MAX_SHOT_DISTANCE,
+        ) else {
+            println!("Hit claim from {} on {} refused", shot.shooter, victim);
+            continue;
+        };
+        // Seulement le decor : les joueurs du monde physique sont a leur position actuelle
+        let not_a_player = |entity| players.get(entity).is_err();
+        let walls = QueryFilter::only_fixed().predicate(&not_a_player);
+        if context
+            .cast_ray(shot.origin, direction, distance, true, walls)
+            .is_some()
+        {
+            continue;
+        }
+
+        let position = shot.origin + direction * distance;
+        damage_events.send(register_hit(
+            &mut server,
+            definition,
+            shot.projectile_id,
+            shot.shooter,
+            victim,
+            position,
+            position.y - hitboxes[&victim].y,
+        ));
+    }
+}
+
+/// Fait voler les projectiles d'un tick. Le decor est teste dans le monde physique,
+/// les joueurs a l'instant du projectile.
+#[allow(clippy::too_many_arguments)]
+pub fn server_update_projectiles(
+    mut server: ResMut<RenetServer>,
+    mut flying: ResMut<ServerProjectiles>,
+    server_tick: Res<ServerTick>,
+    history: Res<HitboxHistory>,
+    context: Res<RapierContext>,
+    players: Query<&ServerPlayer>,
+    weapons: Res<WeaponRegistry>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let dt = 1.0 / server_tick.rate as f32;
+    // Seulement le decor : les joueurs du monde physique sont a leur position actuelle
+    let not_a_player = |entity| players.get(entity).is_err();
+    let walls = QueryFilter::only_fixed().predicate(&not_a_player);
+    flying.projectiles.retain_mut(|projectile| {
+        let (direction, length) = projectile.flight.segment(dt);
+        let wall = context
+            .cast_ray(projectile.flight.position, direction, length, true, walls)
+            .map(|(_, distance)| distance);
+        match projectile.flight.advance(&history, dt, wall) {
+            Flight::Flying => true,
+            Flight::Stopped => false,
+            Flight::Hit {
+                victim,
+                position,
+                height,
+            } => {
+                if let Some(definition) = weapons.get(projectile.weapon) {
+                    damage_events.send(register_hit(
+                        &mut server,
+                        definition,
+                        projectile.projectile_id,
+                        projectile.flight.shooter,
+                        victim,
+                        position,
+                        height,
+                    ));
+                }
+                false
+            }
+        }
+    });
+}
+
+/// Annonce la touche a tous les clients et renvoie les degats a appliquer.
+/// `height` : hauteur de l'impact par rapport au centre de la cible, pour la localisation.
+fn register_hit(
+    server: &mut RenetServer,
+    definition: &WeaponDefinition,
+    projectile_id: ProjectileId,
+    shooter: ClientId,
+    victim: ClientId,
+    position: Vec3,
+    height: f32,
+) -> DamageEvent {
+    let message = ServerMessages::PlayerHit {
+        projectile_id,
+        shooter,
+        victim,
+        position,
+    };
+    server.broadcast_message(message.channel(), protocol::encode(&message));
+    let location = HitLocation::from_height(height);
+    DamageEvent {
+        attacker: shooter,
+        victim,
+        weapon: definition.id,
+        location,
+        amount: definition.damage * location.multiplier(),
+    }
+}
+
+/// Seul endroit ou la vie change : chaque changement est envoye a tous les clients
+pub fn server_apply_damage(
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+    mut damage_events: EventReader<DamageEvent>,
+    server_tick: Res<ServerTick>,
+    mut lobby: ResMut<Lobby>,
+    mut players: Query<(&mut Health, &mut Armor)>,
+) {
+    for damage in damage_events.read() {
+        let Some(victim) = lobby.players.get_mut(&damage.victim) else {
+            continue;
+        };
+        let Some(Ok((mut health, mut armor))) = victim.entity.map(|entity| players.get_mut(entity))
+        else {
+            continue;
+        };
+        // Plusieurs tirs dans le meme tick : un mort ne meurt qu'une fois
+        if health.is_dead() {
+            continue;
+        }
+        health::apply_damage(&mut health, &mut armor, damage.amount);
+        if damage.attacker != damage.victim && !victim.damaged_by.contains(&damage.attacker) {
+            victim.damaged_by.push(damage.attacker);
+        }
+        let message = ServerMessages::HealthChanged {
+            id: damage.victim,
+            health: *health,
+            armor: *armor,
+        };
+        server.broadcast_message(message.channel(), protocol::encode(&message));
+
+        if health.is_dead() {
+            // Le corps n'arrete plus ni les joueurs ni les tirs jusqu'au respawn
+            if let Some(entity) = victim.entity {
+                commands.entity(entity).insert(ColliderDisabled);
+            }
+            victim.reload = None;
+            victim.respawn_tick =
+                Some(server_tick.tick + (RESPAWN_DELAY_SECONDS * server_tick.rate as f32) as u32);
+            let message = ServerMessages::PlayerDeath {
+                killer: damage.attacker,
+                victim: damage.victim,
+                weapon: damage.weapon,
+                headshot: damage.location == HitLocation::Head,
+                respawn_delay: RESPAWN_DELAY_SECONDS,
+            };
+            server.broadcast_message(message.channel(), protocol::encode(&message));
+            lobby.credit_kill(damage.attacker, damage.victim);
+        }
+    }
+}
+
+/// Remplit le chargeur des joueurs dont le rechargement est termine
+pub fn server_finish_reloads(
+    mut server: ResMut<RenetServer>,
+    server_tick: Res<ServerTick>,
+    weapons: Res<WeaponRegistry>,
+    mut lobby: ResMut<Lobby>,
+) {
+    for player in lobby.players.values_mut() {
+        let Some((weapon, tick)) = player.reload else {
+            continue;
+        };
+        if tick > server_tick.tick {
+            continue;
+        }
+        player.reload = None;
+        let Some(definition) = weapons.get(weapon) else {
+            continue;
+        };
+        let ammo = player
+            .ammo
+            .entry(weapon)
+            .or_insert_with(|| Ammo::full(definition));
+        ammo.reload(definition);
+        let message = ServerMessages::AmmoChanged {
+            weapon,
+            ammo: *ammo,
+        };
+        server.send_message(player.id, message.channel(), protocol::encode(&message));
+    }
+}
+
+/// Fait revenir les morts dont le delai est ecoule, avec tout leur etat remis a neuf
+pub fn server_respawn_players(
+    mut commands: Commands,
+    mut server: ResMut<RenetServer>,
+    server_tick: Res<ServerTick>,
+    spawn_points: Res<SpawnPoints>,
+    mut lobby: ResMut<Lobby>,
+    mut players: Query<(&mut Transform, &mut MovementState, &mut Health, &mut Armor)>,
+) {
+    let ready: Vec<(ClientId, Option<u8>)> = lobby
+        .players
+        .values()
+        .filter(|player| player.respawn_tick.is_some_and(|tick| tick <= server_tick.tick))
+        .map(|player| (player.id, player.affiliation.team))
+        .collect();
+    for (id, team) in ready {
+        // Choisi un par un : deux joueurs qui reviennent ensemble ne partent pas du meme point
+        let position = spawn_points.choose(&lobby.enemy_positions(id, team));
+        let Some(player) = lobby.players.get_mut(&id) else {
+            continue;
+        };
+        let Some((entity, Ok((mut transform, mut movement, mut health, mut armor)))) =
+            player.entity.map(|entity| (entity, players.get_mut(entity)))
+        else {
+            continue;
+        };
+        commands.entity(entity).remove::<ColliderDisabled>();
+        *transform = Transform::from_translation(position);
+        *movement = MovementState::default();
+        *health = Health::default();
+        *armor = Armor::default();
+        player.position = *transform;
+        player.respawn_tick = None;
+        // Le client remet aussi ses munitions a neuf en recevant PlayerRespawned
+        player.ammo.clear();
+        player.reload = None;
+
+        let message = ServerMessages::PlayerRespawned { id, position };
+        server.broadcast_message(message.channel(), protocol::encode(&message));
+        let message = ServerMessages::HealthChanged {
+            id,
+            health: *health,
+            armor: *armor,
+        };
+        server.broadcast_message(message.channel(), protocol::encode(&message));
+    }
+}
+
+/// Releve le ping de chaque joueur puis envoie le tableau des scores s'il a change
+pub fn server_send_scoreboard(
+    mut server: ResMut<RenetServer>,
+    server_tick: Res<ServerTick>,
+    mut lobby: ResMut<Lobby>,
+    mut last_sent: Local<Vec<ScoreboardEntry>>,
+) {
+    if server_tick.tick.is_multiple_of(server_tick.rate * PING_INTERVAL_SECONDS) {
+        for player in lobby.players.values_mut() {
+            if let Ok(info) = server.network_info(player.id) {
+                player.ping = (info.rtt * 1000.0).round() as u32;
+            }
+        }
+    }
+    let entries = lobby.scoreboard();
+    if entries == *last_sent {
+        return;
+    }
+    let message = ServerMessages::Scoreboard { players: entries.clone() };
+    server.broadcast_message(message.channel(), protocol::encode(&message));
+    *last_sent = entries;
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    f32::consts::TAU,
+    fmt,
+};
+
+use bevy::{
+    math::{EulerRot, Vec3},
+    prelude::{Resource, Transform},
+};
+use renet::ClientId;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+// Precision des positions envoyees : 1/256 m, soit 4 mm
+const POSITION_SCALE: f32 = 256.0;
+// Un tour complet tient sur 16 bits
+const YAW_SCALE: f32 = 65536.0 / TAU;
+/// Snapshots gardes comme reference possible d'un delta : environ 1 s a 60 Hz, la frequence
+/// par defaut, et 2 s a 30 Hz.
+/// Un client qui n'a rien confirme depuis plus longtemps recoit un snapshot complet.
+pub const SNAPSHOT_HISTORY: usize = 64;
+
+const CHANGED_X: u8 = 1 << 0;
+const CHANGED_Y: u8 = 1 << 1;
+const CHANGED_Z: u8 = 1 << 2;
+const CHANGED_YAW: u8 = 1 << 3;
+const FIELDS: [u8; 4] = [CHANGED_X, CHANGED_Y, CHANGED_Z, CHANGED_YAW];
+const ALL_FIELDS: u8 = CHANGED_X | CHANGED_Y | CHANGED_Z | CHANGED_YAW;
+
+/// Etat d'un joueur tel qu'il part sur le reseau
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QuantizedPlayer {
+    pub position: [i32; 3],
+    pub yaw: u16,
+}
+
+impl QuantizedPlayer {
+    pub fn new(position: Vec3, yaw: f32) -> Self {
+        QuantizedPlayer {
+            position: position.to_array().map(|axis| (axis * POSITION_SCALE).round() as i32),
+            yaw: (yaw.rem_euclid(TAU) * YAW_SCALE).round() as u32 as u16,
+        }
+    }
+
+    pub fn from_transform(transform: &Transform) -> Self {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        QuantizedPlayer::new(transform.translation, yaw)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        Vec3::from_array(self.position.map(|axis| axis as f32 / POSITION_SCALE))
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw as f32 / YAW_SCALE
+    }
+
+    fn fields(&self) -> [i32; 4] {
+        [self.position[0], self.position[1], self.position[2], self.yaw as i32]
+    }
+}
+
+/// Tous les joueurs d'un tick, ranges par id pour que le client et le serveur les parcourent pareil
+pub type WorldState = BTreeMap<ClientId, QuantizedPlayer>;
+
+/// Ecart d'un joueur avec le snapshot de reference. `values` est indexe comme `FIELDS`,
+/// seuls les champs du masque `changed` sont envoyes, dans une sequence avec sa longueur.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerDelta {
+    pub id: ClientId,
+    pub changed: u8,
+    pub values: [i32; 4],
+}
+
+/// Ecarts de `current` par rapport a `baseline` : joueurs partis, puis joueurs nouveaux ou modifies.
+/// `skip` est le joueur qui recoit le snapshot, sa position exacte est deja dans l'`InputAck`.
+pub fn diff(
+    baseline: &WorldState,
+    current: &WorldState,
+    skip: ClientId,
+) -> (Vec<ClientId>, Vec<PlayerDelta>) {
+    let removed = baseline
+        .keys()
+        .filter(|id| **id != skip && !current.contains_key(id))
+        .copied()
+        .collect();
+
+    let mut changed = Vec::new();
+    for (id, player) in current.iter().filter(|(id, _)| **id != skip) {
+        let base = baseline.get(id);
+        let base_fields = base.copied().unwrap_or_default().fields();
+        let mut delta = PlayerDelta {
+            id: *id,
+            changed: 0,
+            values: [0; 4],
+        };
+        for (i, field) in player.fields().into_iter().enumerate() {
+            let value = if FIELDS[i] == CHANGED_YAW {
+                // Le plus court chemin autour du cercle
+                (field as u16).wrapping_sub(base_fields[i] as u16) as i16 as i32
+            } else {
+                field.wrapping_sub(base_fields[i])
+            };
+            if value != 0 {
+                delta.changed |= FIELDS[i];
+                delta.values[i] = value;
+            }
+        }
+        // Un nouveau joueur est toujours envoye, meme s'il est a l'origine
+        if delta.changed != 0 || base.is_none() {
+            changed.push(delta);
+        }
+    }
+    (removed, changed)
+}
+
+/// Inverse de `diff`. Les valeurs viennent du reseau : tout est en arithmetique modulaire.
+pub fn apply(baseline: &WorldState, removed: &[ClientId], changed: &[PlayerDelta]) -> WorldState {
+    let mut state = baseline.clone();
+    for id in removed {
+        state.remove(id);
+    }
+    for delta in changed {
+        let player = state.entry(delta.id).or_default();
+        for (i, axis) in player.position.iter_mut().enumerate() {
+            *axis = axis.wrapping_add(delta.values[i]);
+        }
+        player.yaw = player.yaw.wrapping_add(delta.values[3] as u16);
+    }
+    state
+}
+
+impl Serialize for PlayerDelta {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let count = FIELDS.iter().filter(|field| self.changed & **field != 0).count();
+        let mut seq = serializer.serialize_seq(Some(2 + count))?;
+        seq.serialize_element(&self.id)?;
+        seq.serialize_element(&self.changed)?;
+        for (i, field) in FIELDS.iter().enumerate() {
+            if self.changed & field != 0 {
+                seq.serialize_element(&self.values[i])?;
+            }
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PlayerDelta {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DeltaVisitor;
+
+        impl<'de> Visitor<'de> for DeltaVisitor {
+            type Value = PlayerDelta;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a player id, a field mask and the changed fields")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PlayerDelta, A::Error> {
+                let id = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let changed: u8 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                if changed & !ALL_FIELDS != 0 {
+                    return Err(de::Error::custom("unknown field in snapshot mask"));
+                }
+                // La longueur annoncee doit correspondre au masque
+                let count = FIELDS.iter().filter(|field| changed & **field != 0).count();
+                if let Some(remaining) = seq.size_hint().filter(|remaining| *remaining != count) {
+                    return Err(de::Error::invalid_length(2 + remaining, &self));
+                }
+                let mut values = [0; 4];
+                for (i, field) in FIELDS.iter().enumerate() {
+                    if changed & field != 0 {
+                        values[i] = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(2 + i, &self))?;
+                    }
+                }
+                Ok(PlayerDelta {
+                    id,
+                    changed,
+                    values,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(DeltaVisitor)
+    }
+}
+
+/// Derniers etats du monde, par tick : envoyes cote serveur, recus cote client
+#[derive(Debug, Default, Resource)]
+pub struct SnapshotHistory {
+    states: VecDeque<(u32, WorldState)>,
+}
+
+impl SnapshotHistory {
+    pub fn push(&mut self, tick: u32, state: WorldState) {
+        if self.states.len() == SNAPSHOT_HISTORY {
+            self.states.pop_front();
+        }
+        self.states.push_back((tick, state));
+    }
+
+    pub fn get(&self, tick: u32) -> Option<&WorldState> {
+        self.states
+            .iter()
+            .find(|(state_tick, _)| *state_tick == tick)
+            .map(|(_, state)| state)
+    }
+}
@@ -1,5 +1,24 @@
-use bevy::{prelude::*, window::PrimaryWindow};
-use renet::RenetClient;
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use renet::{transport::NetcodeClientTransport, RenetClient};
+
+use std::io;
+
+use crate::{
+    client,
+    connection::{ConnectionSettings, DEFAULT_PORT},
+    server,
+};
+
+// Meme delai que le netcode en mode non securise
+const JOIN_TIMEOUT: f32 = 5.0;
 
 // #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 // pub enum HostState {
@@ -8,21 +27,12 @@ use renet::RenetClient;
 //     Join,
 // }
 
-#[derive(Component, Resource)]
+#[derive(Default, Component, Resource)]
 pub struct HostState {
     pub is_host: bool,
     pub is_host_initialized: bool,
 }
 
-impl Default for HostState {
-    fn default() -> Self {
-        HostState {
-            is_host: false,
-            is_host_initialized: false,
-        }
-    }
-}
-
 // Enum that will be used as a global state for the game
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum GameState {
@@ -35,7 +45,10 @@ pub enum GameState {
 pub enum Buttons {
     Start,
     Join,
+    Connect,
     Create,
+    Ipv6,
+    HostField(HostField),
     Back,
     Settings,
     Quit,
@@ -57,6 +70,97 @@ pub struct StartMenu;
 #[derive(Component)]
 pub struct SettingsMenu;
 
+#[derive(Component)]
+pub struct JoinMenu;
+
+/// Texte de l'ecran "Join" qui affiche l'adresse du serveur en cours de saisie
+#[derive(Component)]
+pub struct ServerAddressText;
+
+#[derive(Component)]
+pub struct JoinStatusText;
+
+/// Texte du menu "Start" ou s'affiche l'echec de "Create"
+#[derive(Component)]
+pub struct CreateErrorText;
+
+#[derive(Component)]
+pub struct ConnectButtonText;
+
+/// Avancement de la connexion lancee depuis l'ecran "Join"
+#[derive(Resource, Default)]
+pub enum JoinStatus {
+    #[default]
+    Idle,
+    Connecting(Timer),
+    Failed(String),
+}
+
+#[derive(Component)]
+pub struct IpVersionText;
+
+/// Champ du menu "Start" pour heberger une partie, porte aussi par son texte
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HostField {
+    Bind,
+    Public,
+    Port,
+}
+
+/// Adresses de la partie hebergee en cours de saisie, appliquees au clic sur "Create".
+/// Separees du serveur a rejoindre, qui se tape dans l'ecran "Join".
+#[derive(Resource, Default)]
+pub struct HostFields {
+    pub bind: String,
+    pub public: String,
+    pub port: String,
+    pub focus: Option<HostField>,
+}
+
+impl HostFields {
+    pub fn from_settings(connection_settings: &ConnectionSettings) -> Self {
+        let ip = |ip: Option<std::net::IpAddr>| ip.map(|ip| ip.to_string()).unwrap_or_default();
+        HostFields {
+            bind: ip(connection_settings.bind_address),
+            public: ip(connection_settings.public_address),
+            port: connection_settings.port.to_string(),
+            focus: None,
+        }
+    }
+
+    fn value(&self, field: HostField) -> &String {
+        match field {
+            HostField::Bind => &self.bind,
+            HostField::Public => &self.public,
+            HostField::Port => &self.port,
+        }
+    }
+
+    fn value_mut(&mut self, field: HostField) -> &mut String {
+        match field {
+            HostField::Bind => &mut self.bind,
+            HostField::Public => &mut self.public,
+            HostField::Port => &mut self.port,
+        }
+    }
+
+    fn label(&self, field: HostField) -> String {
+        let value = self.value(field);
+        let (name, placeholder) = match field {
+            HostField::Bind => ("Bind", "all interfaces".to_string()),
+            HostField::Public => ("Public", "local ip".to_string()),
+            HostField::Port => ("Port", DEFAULT_PORT.to_string()),
+        };
+        if self.focus == Some(field) {
+            format!("{}: {}_", name, value)
+        } else if value.is_empty() {
+            format!("{}: {}", name, placeholder)
+        } else {
+            format!("{}: {}", name, value)
+        }
+    }
+}
+
 #[derive(Component)]
 pub enum PlayerKeys {
     Forward,
@@ -186,6 +290,7 @@ pub fn create_main_menu(commands: &mut Commands, asset_server: &Res<AssetServer>
         });
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &Buttons),
@@ -198,9 +303,13 @@ pub fn button_system(
     query_camera: Query<Entity, With<MenuCamera>>,
     query_start: Query<Entity, With<StartMenu>>,
     query_settings: Query<Entity, With<SettingsMenu>>,
+    query_join: Query<Entity, With<JoinMenu>>,
     asset_server: Res<AssetServer>,
-    client: Res<RenetClient>,
     mut host_state: ResMut<HostState>,
+    mut connection_settings: ResMut<ConnectionSettings>,
+    mut join_status: ResMut<JoinStatus>,
+    mut host_fields: ResMut<HostFields>,
+    mut create_error_text: Query<&mut Text, With<CreateErrorText>>,
 ) {
     for (interaction, mut color, button_action) in interaction_query.iter_mut() {
         // let mut error_text: EntityCommands;
@@ -214,7 +323,13 @@ pub fn button_system(
                     Buttons::Start => {
                         println!("Button 1 clicked! Perform action for Button 1.");
                         clear_main_menu(&mut commands, &query);
-                        create_start_menu(&mut commands, &asset_server);
+                        *host_fields = HostFields::from_settings(&connection_settings);
+                        create_start_menu(
+                            &mut commands,
+                            &asset_server,
+                            &host_fields,
+                            &connection_settings,
+                        );
                     }
                     Buttons::Settings => {
                         println!("Button 2 clicked! Perform action for Button 2.");
@@ -227,30 +342,84 @@ pub fn button_system(
                     }
 
                     Buttons::Join => {
-                        if client.is_connected() {
-                            next_state.set(GameState::Game);
+                        *join_status = JoinStatus::Idle;
+                        clear_start_menu(&mut commands, &query_start);
+                        create_join_menu(&mut commands, &asset_server, &connection_settings);
+                    }
+                    Buttons::Connect => {
+                        // Sert aussi de "Retry" apres un echec
+                        *join_status = match connection_settings
+                            .resolve_server_addr()
+                            .and_then(|server_addr| {
+                                client::connect_to_server(
+                                    &mut commands,
+                                    &connection_settings,
+                                    server_addr,
+                                )
+                            }) {
+                            Ok(()) => JoinStatus::Connecting(Timer::from_seconds(
+                                JOIN_TIMEOUT,
+                                TimerMode::Once,
+                            )),
+                            Err(error) => JoinStatus::Failed(error.to_string()),
+                        };
+                    }
+                    Buttons::Create => {
+                        let hosting = if host_state.is_host {
+                            Ok(())
                         } else {
-                            commands.spawn(TextBundle {
-                                text: Text {
-                                    sections: vec![TextSection {
-                                        value: "Error when trying to connect to the server"
-                                            .to_string(),
-                                        style: TextStyle {
-                                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                            font_size: 40.0,
-                                            color: Color::srgb(1., 0., 0.),
-                                        },
-                                    }],
-                                    ..Default::default()
-                                },
-                                ..Default::default()
-                            });
+                            // Nouvelle connexion vers le serveur qui vient de demarrer
+                            connection_settings
+                                .set_host_addresses(
+                                    &host_fields.bind,
+                                    &host_fields.public,
+                                    &host_fields.port,
+                                )
+                                .map_err(|error| {
+                                    io::Error::new(io::ErrorKind::InvalidInput, error)
+                                })
+                                .and_then(|_| server::spawn_listen_server(&connection_settings))
+                                .and_then(|listen_server| {
+                                    client::connect_to_server(
+                                        &mut commands,
+                                        &connection_settings,
+                                        connection_settings.local_server_addr(),
+                                    )
+                                    // Sinon le serveur garde le port et le prochain essai echoue
+                                    .inspect_err(|_| listen_server.shutdown())
+                                })
+                        };
+                        match hosting {
+                            Ok(()) => {
+                                host_state.is_host = true;
+                                next_state.set(GameState::Game);
+                            }
+                            Err(error) => {
+                                for mut text in create_error_text.iter_mut() {
+                                    text.sections[0].value =
+                                        format!("Error when trying to create the server: {}", error);
+                                }
+                            }
                         }
                     }
-                    Buttons::Create => {
-                        host_state.is_host = true;
-                        next_state.set(GameState::Game);
-                        // error_text.despawn_recursive();
+                    Buttons::Ipv6 => {
+                        connection_settings.toggle_ipv6();
+                    }
+                    Buttons::HostField(field) => {
+                        host_fields.focus = Some(*field);
+                    }
+                    Buttons::Back if !query_join.is_empty() => {
+                        if matches!(*join_status, JoinStatus::Connecting(_)) {
+                            client::disconnect_from_server(&mut commands);
+                        }
+                        *join_status = JoinStatus::Idle;
+                        clear_join_menu(&mut commands, &query_join);
+                        create_start_menu(
+                            &mut commands,
+                            &asset_server,
+                            &host_fields,
+                            &connection_settings,
+                        );
                     }
                     Buttons::Back => {
                         clear_start_menu(&mut commands, &query_start);
@@ -272,6 +441,159 @@ pub fn button_system(
     }
 }
 
+/// Suit la connexion lancee par "Connect" : lance la partie une fois connecte,
+/// sinon garde la raison de la deconnexion pour l'afficher
+pub fn update_join_status(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut join_status: ResMut<JoinStatus>,
+    mut next_state: ResMut<NextState<GameState>>,
+    client: Option<Res<RenetClient>>,
+    transport: Option<Res<NetcodeClientTransport>>,
+) {
+    let JoinStatus::Connecting(timer) = join_status.as_mut() else {
+        return;
+    };
+    let (Some(client), Some(transport)) = (client, transport) else {
+        return;
+    };
+    timer.tick(time.delta());
+
+    if client.is_connected() {
+        *join_status = JoinStatus::Idle;
+        next_state.set(GameState::Game);
+        return;
+    }
+
+    let reason = if let Some(reason) = transport.disconnect_reason() {
+        Some(reason.to_string())
+    } else if let Some(reason) = client.disconnect_reason() {
+        Some(reason.to_string())
+    } else if timer.finished() {
+        Some("no answer from the server".to_string())
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        client::disconnect_from_server(&mut commands);
+        *join_status = JoinStatus::Failed(reason);
+    }
+}
+
+pub fn update_join_texts(
+    join_status: Res<JoinStatus>,
+    mut status_text: Query<&mut Text, (With<JoinStatusText>, Without<ConnectButtonText>)>,
+    mut connect_text: Query<&mut Text, (With<ConnectButtonText>, Without<JoinStatusText>)>,
+) {
+    let (status, color, connect_label) = match join_status.as_ref() {
+        JoinStatus::Idle => (String::new(), Color::WHITE, "Connect"),
+        JoinStatus::Connecting(timer) => (
+            format!("Connecting... {:.0}s", timer.remaining_secs().ceil()),
+            Color::WHITE,
+            "Connect",
+        ),
+        JoinStatus::Failed(reason) => (
+            format!("Connection failed: {}", reason),
+            Color::srgb(1., 0., 0.),
+            "Retry",
+        ),
+    };
+    for mut text in status_text.iter_mut() {
+        text.sections[0].value = status.clone();
+        text.sections[0].style.color = color;
+    }
+    for mut text in connect_text.iter_mut() {
+        text.sections[0].value = connect_label.to_string();
+    }
+}
+
+/// Saisie au clavier de l'adresse du serveur tant que l'ecran "Join" est ouvert
+pub fn server_address_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut connection_settings: ResMut<ConnectionSettings>,
+    query_join: Query<Entity, With<JoinMenu>>,
+) {
+    if query_join.is_empty() {
+        keyboard_events.clear();
+        return;
+    }
+    let mut address = connection_settings.server_address.clone();
+    type_address(&mut keyboard_events, &mut address);
+    if address != connection_settings.server_address {
+        connection_settings.server_address = address;
+    }
+}
+
+/// Saisie au clavier du champ selectionne dans le menu "Start"
+pub fn host_address_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut host_fields: ResMut<HostFields>,
+    query_start: Query<Entity, With<StartMenu>>,
+) {
+    let Some(field) = host_fields.focus.filter(|_| !query_start.is_empty()) else {
+        keyboard_events.clear();
+        return;
+    };
+    let mut value = host_fields.value(field).clone();
+    type_address(&mut keyboard_events, &mut value);
+    if value != *host_fields.value(field) {
+        *host_fields.value_mut(field) = value;
+    }
+}
+
+/// Efface ou ajoute les caracteres tapes qui peuvent apparaitre dans une adresse
+fn type_address(keyboard_events: &mut EventReader<KeyboardInput>, address: &mut String) {
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                address.pop();
+            }
+            Key::Character(characters) => address.extend(
+                characters
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || ".:-[]".contains(*c)),
+            ),
+            _ => {}
+        }
+    }
+}
+
+pub fn update_connection_texts(
+    connection_settings: Res<ConnectionSettings>,
+    mut address_text: Query<&mut Text, (With<ServerAddressText>, Without<IpVersionText>)>,
+    mut ip_version_text: Query<&mut Text, (With<IpVersionText>, Without<ServerAddressText>)>,
+) {
+    for mut text in address_text.iter_mut() {
+        text.sections[0].value = format!("Server: {}", connection_settings.server_address);
+    }
+    for mut text in ip_version_text.iter_mut() {
+        text.sections[0].value = ip_version_label(&connection_settings).to_string();
+    }
+}
+
+pub fn update_host_field_texts(
+    host_fields: Res<HostFields>,
+    mut texts: Query<(&mut Text, &HostField)>,
+) {
+    if !host_fields.is_changed() {
+        return;
+    }
+    for (mut text, field) in texts.iter_mut() {
+        text.sections[0].value = host_fields.label(*field);
+    }
+}
+
+fn ip_version_label(connection_settings: &ConnectionSettings) -> &'static str {
+    if connection_settings.ipv6 {
+        "IPv6"
+    } else {
+        "IPv4"
+    }
+}
+
 fn close(app_exit_events: &mut EventWriter<AppExit>) {
     app_exit_events.send(AppExit::default());
 }
@@ -281,9 +603,11 @@ pub fn clear(
     query_menu: Query<Entity, With<MainMenu>>,
     query_camera: Query<Entity, With<MenuCamera>>,
     query_start: Query<Entity, With<StartMenu>>,
+    query_join: Query<Entity, With<JoinMenu>>,
 ) {
     clear_main_menu(&mut commands, &query_menu);
     clear_start_menu(&mut commands, &query_start);
+    clear_join_menu(&mut commands, &query_join);
     clear_menu_camera(&mut commands, &query_camera);
 }
 
@@ -299,6 +623,12 @@ pub fn clear_start_menu(commands: &mut Commands, query: &Query<Entity, With<Star
     }
 }
 
+pub fn clear_join_menu(commands: &mut Commands, query: &Query<Entity, With<JoinMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 pub fn clear_settings_menu(commands: &mut Commands, query: &Query<Entity, With<SettingsMenu>>) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
@@ -318,7 +648,12 @@ pub fn hide_cursor(mut windows: Query<&mut Window, With<PrimaryWindow>>) {
     }
 }
 
-pub fn create_start_menu(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+pub fn create_start_menu(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    host_fields: &HostFields,
+    connection_settings: &ConnectionSettings,
+) {
     let start_menu = NodeBundle {
         style: Style {
             width: Val::Percent(100.0),
@@ -388,9 +723,71 @@ pub fn create_start_menu(commands: &mut Commands, asset_server: &Res<AssetServer
         ..default()
     };
 
+    let error_text = TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: String::new(),
+                style: TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::srgb(1., 0., 0.),
+                },
+            }],
+            ..Default::default()
+        },
+        ..default()
+    };
+
+    // Plus large que les boutons : une adresse IPv6 doit tenir
+    let field_button = ButtonBundle {
+        style: Style {
+            width: Val::Px(460.0),
+            height: Val::Px(50.0),
+            margin: UiRect::vertical(Val::Px(5.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let field_text = |value: String| TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value,
+                style: TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                },
+            }],
+            ..Default::default()
+        },
+        ..default()
+    };
+
+    let ip_version_text = TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value: ip_version_label(connection_settings).to_string(),
+                style: TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            }],
+            ..Default::default()
+        },
+        ..default()
+    };
+
     commands
         .spawn(start_menu)
         .insert(StartMenu)
+        .with_children(|parent| {
+            // Enfant du menu : remplace a chaque echec, disparait avec l'ecran
+            parent.spawn((error_text, CreateErrorText));
+        })
         .with_children(|parent| {
             parent
                 .spawn(default_button.clone())
@@ -407,6 +804,24 @@ pub fn create_start_menu(commands: &mut Commands, asset_server: &Res<AssetServer
                     button.spawn(create_text);
                 });
         })
+        .with_children(|parent| {
+            for field in [HostField::Bind, HostField::Public, HostField::Port] {
+                parent
+                    .spawn(field_button.clone())
+                    .insert(Buttons::HostField(field))
+                    .with_children(|button| {
+                        button.spawn(field_text(host_fields.label(field))).insert(field);
+                    });
+            }
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(default_button.clone())
+                .insert(Buttons::Ipv6)
+                .with_children(|button| {
+                    button.spawn(ip_version_text).insert(IpVersionText);
+                });
+        })
         .with_children(|parent| {
             parent
                 .spawn(default_button.clone())
@@ -417,6 +832,86 @@ pub fn create_start_menu(commands: &mut Commands, asset_server: &Res<AssetServer
         });
 }
 
+pub fn create_join_menu(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    connection_settings: &ConnectionSettings,
+) {
+    let join_menu = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let default_button = ButtonBundle {
+        style: Style {
+            width: Val::Px(200.0),
+            height: Val::Px(80.0),
+            margin: UiRect::vertical(Val::Px(5.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let menu_text = |value: String, font_size: f32| TextBundle {
+        text: Text {
+            sections: vec![TextSection {
+                value,
+                style: TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size,
+                    color: Color::WHITE,
+                },
+            }],
+            ..Default::default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn(join_menu)
+        .insert(JoinMenu)
+        .with_children(|parent| {
+            parent
+                .spawn(menu_text(
+                    format!("Server: {}", connection_settings.server_address),
+                    30.0,
+                ))
+                .insert(ServerAddressText);
+            parent
+                .spawn(menu_text(String::new(), 30.0))
+                .insert(JoinStatusText);
+            parent
+                .spawn(default_button.clone())
+                .insert(Buttons::Connect)
+                .with_children(|button| {
+                    button
+                        .spawn(menu_text("Connect".to_string(), 40.0))
+                        .insert(ConnectButtonText);
+                });
+            parent
+                .spawn(default_button.clone())
+                .insert(Buttons::Back)
+                .with_children(|button| {
+                    button.spawn(menu_text("Back".to_string(), 40.0));
+                });
+        });
+}
+
+// Menu en chantier : les bundles inutilises attendent le menu des touches commente plus bas
+#[allow(
+    clippy::unnecessary_operation,
+    clippy::let_unit_value,
+    clippy::needless_borrow
+)]
 pub fn create_settings_menu(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     let settings_menu = NodeBundle {
         style: Style {
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use renet::transport::NETCODE_USER_DATA_BYTES;
+
+/// Pseudo par defaut quand celui envoye par le client est refuse
+pub const DEFAULT_USERNAME: &str = "Player";
+pub const MAX_USERNAME_LEN: usize = 16;
+
+/// `user_data` du netcode : la longueur sur un octet puis le pseudo en UTF-8
+pub type UserData = [u8; NETCODE_USER_DATA_BYTES];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsernameError {
+    Empty,
+    TooLong,
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UsernameError::Empty => write!(f, "pseudo vide"),
+            UsernameError::TooLong => {
+                write!(f, "pseudo de plus de {} caracteres", MAX_USERNAME_LEN)
+            }
+            UsernameError::InvalidCharacter(c) => {
+                write!(f, "caractere interdit dans le pseudo : {:?}", c)
+            }
+        }
+    }
+}
+
+/// Ecrit le pseudo dans le `user_data`, coupe s'il ne rentre pas : le serveur le refusera de toute facon
+pub fn to_user_data(username: &str) -> UserData {
+    let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+    let bytes = username.as_bytes();
+    let len = bytes.len().min(NETCODE_USER_DATA_BYTES - 1);
+    user_data[0] = len as u8;
+    user_data[1..=len].copy_from_slice(&bytes[..len]);
+    user_data
+}
+
+/// Pseudo ecrit par `to_user_data`, `None` si les octets n'en sont pas un
+pub fn from_user_data(user_data: &UserData) -> Option<String> {
+    let len = user_data[0] as usize;
+    let bytes = user_data.get(1..=len)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// Lettres et chiffres ASCII, `-` et `_`. Pas d'unicode ni d'espace : un "a" cyrillique ou un
+/// double espace donnerait un pseudo identique a l'oeil a celui d'un autre joueur.
+/// Les espaces autour du pseudo sont retires.
+pub fn validate(username: &str) -> Result<String, UsernameError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(UsernameError::Empty);
+    }
+    if username.chars().count() > MAX_USERNAME_LEN {
+        return Err(UsernameError::TooLong);
+    }
+    if let Some(c) = username
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(UsernameError::InvalidCharacter(c));
+    }
+    Ok(username.to_string())
+}
+
+/// Ajoute un numero si le pseudo est deja pris (sans tenir compte de la casse),
+/// en raccourcissant le pseudo pour rester sous `MAX_USERNAME_LEN`
+pub fn make_unique<'a>(username: &str, taken: impl Iterator<Item = &'a str> + Clone) -> String {
+    let is_taken = |name: &str| {
+        taken
+            .clone()
+            .any(|other| other.to_lowercase() == name.to_lowercase())
+    };
+    if !is_taken(username) {
+        return username.to_string();
+    }
+    (2..)
+        .map(|n| {
+            let suffix = n.to_string();
+            let base: String = username
+                .chars()
+                .take(MAX_USERNAME_LEN - suffix.len())
+                .collect();
+            format!("{}{}", base, suffix)
+        })
+        .find(|name| !is_taken(name))
+        .unwrap()
+}
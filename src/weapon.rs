@@ -1,18 +1,33 @@
 // use bevy::input::mouse;
+use std::f32::consts::PI;
+
+use bevy::audio::Volume;
 use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 use bevy::render::view::RenderLayers;
-use renet::transport::NetcodeClientTransport;
-use renet::RenetClient;
+use bevy_rapier3d::prelude::RapierContext;
+use renet::{transport::NetcodeClientTransport, RenetClient};
 
+use crate::ammo::LocalAmmo;
+use crate::inventory::Inventory;
+use crate::camera::WorldModelCamera;
+use crate::client::{ClientMessages, Entities, ServerClock};
+use crate::hitscan::fire_hitscan;
 use crate::keybind::KeyBinds;
 use crate::player::*;
 use crate::projectile::*;
+use crate::protocol;
+use crate::weapon_definition::{FireMode, WeaponRegistry};
 
 /// Used by the view model camera and the player's arm.
 /// The light source belongs to both layers.
 const VIEW_MODEL_RENDER_LAYER: usize = 1;
 
+/// Identifiant d'arme envoye sur le reseau
+pub type WeaponId = u8;
+/// Arme du premier emplacement, decrite dans `assets/weapons/blaster.weapon.ron`
+pub const DEFAULT_WEAPON: WeaponId = 0;
+
 #[derive(Debug)]
 pub struct FireRateTimer {
     pub timer: Timer,
@@ -21,7 +36,8 @@ pub struct FireRateTimer {
 impl Default for FireRateTimer {
     fn default() -> Self {
         FireRateTimer {
-            timer: Timer::from_seconds(0.3, TimerMode::Repeating), // 0.3 seconde entre chaque tir
+            // Pret a tirer des le depart, la cadence de l'arme s'applique apres le premier tir
+            timer: Timer::from_seconds(0.0, TimerMode::Once),
         }
     }
 }
@@ -53,45 +69,144 @@ pub fn spawn_weapon(
     ));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn pew(
     key_binds: Res<KeyBinds>,
-    input: Res<ButtonInput<MouseButton>>,
+    (input, key_input): (Res<ButtonInput<MouseButton>>, Res<ButtonInput<KeyCode>>),
     mut commands: Commands<'_, '_>,
-    meshes: ResMut<'_, Assets<Mesh>>,
-    materials: ResMut<'_, Assets<StandardMaterial>>,
+    mut meshes: ResMut<'_, Assets<Mesh>>,
+    mut materials: ResMut<'_, Assets<StandardMaterial>>,
     player_query: Query<'_, '_, &Transform, With<Player>>,
     weapon_query: Query<'_, '_, (&Transform, &Parent), With<Weapon>>,
     time: Res<Time>,
     mut fire_rate_timer: Local<FireRateTimer>,
     asset_server: Res<AssetServer>,
-    client: ResMut<RenetClient>,
-    transport: ResMut<NetcodeClientTransport>,
+    mut client: ResMut<RenetClient>,
+    transport: Res<NetcodeClientTransport>,
+    clock: Res<ServerClock>,
+    mut shot: Local<u32>,
+    (weapons, mut local_ammo, inventory, player_state): (
+        Res<WeaponRegistry>,
+        ResMut<LocalAmmo>,
+        Res<Inventory>,
+        Res<PlayerState>,
+    ),
+    // Tir instantane : rayon depuis la camera contre le monde physique
+    (rapier_context, entities, camera): (
+        Res<RapierContext>,
+        Res<Entities>,
+        Query<&GlobalTransform, With<WorldModelCamera>>,
+    ),
 ) {
-    if input.pressed(key_binds.shoot) && fire_rate_timer.timer.tick(time.delta()).just_finished() {
-        fire_rate_timer.timer = Timer::from_seconds(0.08, TimerMode::Once);
+    // Pas de tir tant que les armes ne sont pas chargees
+    let Some(definition) = weapons.get(inventory.weapon()) else {
+        return;
+    };
+    fire_rate_timer.timer.tick(time.delta());
+    // Le serveur ignorerait le tir ou le rechargement sans corriger les munitions
+    if *player_state == PlayerState::Dead
+        || local_ammo.is_reloading()
+        || inventory.is_switching()
+    {
+        return;
+    }
+
+    // Rechargement a la demande, ou tout seul quand le chargeur est vide
+    let ammo = local_ammo.get(definition);
+    if (key_input.just_pressed(key_binds.reload) || ammo.magazine == 0)
+        && ammo.can_reload(definition)
+    {
+        local_ammo.reload = Some((
+            definition.id,
+            Timer::from_seconds(definition.reload_time, TimerMode::Once),
+        ));
+        let message = ClientMessages::Reload {
+            weapon: definition.id,
+        };
+        client.send_message(message.channel(), protocol::encode(&message));
+        return;
+    }
+
+    if input.pressed(key_binds.shoot) && fire_rate_timer.timer.finished() {
+        if !local_ammo.get_mut(definition).try_fire() {
+            // Plus rien, meme en reserve : un clic a chaque appui
+            if input.just_pressed(key_binds.shoot) {
+                commands.spawn(AudioBundle {
+                    source: asset_server.load(&definition.dry_fire_sound),
+                    settings: PlaybackSettings::DESPAWN
+                        .with_speed(2.5)
+                        .with_volume(Volume::new(0.3)),
+                });
+            }
+            return;
+        }
+        fire_rate_timer.timer = Timer::from_seconds(definition.fire_interval, TimerMode::Once);
 
         commands.spawn(AudioBundle {
-            source: asset_server.load("sounds/0437.ogg"),
+            source: asset_server.load(&definition.sound),
             ..default()
         });
-        spawn_projectile(commands, meshes, materials, weapon_query, player_query, client, transport);
+        match definition.fire_mode {
+            FireMode::Projectile => {
+                *shot += 1;
+                spawn_projectile(
+                    commands,
+                    meshes,
+                    materials,
+                    weapon_query,
+                    player_query,
+                    client,
+                    transport,
+                    clock,
+                    time,
+                    *shot,
+                    definition,
+                );
+            }
+            FireMode::Hitscan => fire_hitscan(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut client,
+                &rapier_context,
+                &entities,
+                &camera,
+                &weapon_query,
+                &player_query,
+                clock.render_time(time.elapsed_seconds_f64()),
+                definition,
+            ),
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_arm(
     key_binds: Res<KeyBinds>,
     time: Res<Time>,
     key_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    local_ammo: Res<LocalAmmo>,
+    inventory: Res<Inventory>,
     mut arm_query: Query<&mut Transform, With<Weapon>>,
     mut swing_state: Local<f32>,
-    mut initial_x: Local<Option<f32>>,
+    mut initial_translation: Local<Option<Vec3>>,
 ) {
     if let Ok(mut arm_transform) = arm_query.get_single_mut() {
-        if initial_x.is_none() {
-            *initial_x = Some(arm_transform.translation.x);
+        if initial_translation.is_none() {
+            *initial_translation = Some(arm_transform.translation);
         }
-        let init_x = initial_x.unwrap();
+        let init_x = initial_translation.unwrap().x;
+        let init_y = initial_translation.unwrap().y;
+
+        // Rechargement : le bras descend en basculant puis remonte.
+        // Changement d'arme : la nouvelle arme remonte depuis le bas.
+        let dip = local_ammo
+            .reload_progress()
+            .map_or(0.0, |progress| (progress * PI).sin())
+            .max(inventory.switch_progress().map_or(0.0, |progress| 1.0 - progress));
+        arm_transform.translation.y = init_y - dip * 0.15;
+        arm_transform.rotation = Quat::from_rotation_x(-dip * 0.8);
 
         let is_aiming = mouse_input.pressed(key_binds.aim);
 
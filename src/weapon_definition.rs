@@ -0,0 +1,217 @@
+use std::{collections::HashMap, fmt, io};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::weapon::WeaponId;
+
+/// Dossier lu au demarrage, par le serveur comme par les clients
+pub const WEAPONS_FOLDER: &str = "weapons";
+
+/// Caracteristiques d'une arme, lues dans `assets/weapons/*.weapon.ron`
+#[derive(Debug, Clone, PartialEq, Deserialize, Asset, TypePath)]
+pub struct WeaponDefinition {
+    // Identifiant envoye sur le reseau, unique parmi les fichiers
+    pub id: WeaponId,
+    pub name: String,
+    pub fire_mode: FireMode,
+    // Secondes entre deux tirs
+    pub fire_interval: f32,
+    // Ignores par une arme `Hitscan`
+    pub projectile_speed: f32,
+    // Secondes avant que le projectile disparaisse s'il n'a rien touche
+    pub projectile_lifetime: f32,
+    // Degats d'une balle dans le corps, avant l'armure
+    pub damage: f32,
+    // Decalage aleatoire maximal ajoute a la direction du tir, sur chaque axe
+    pub spread: f32,
+    pub magazine_size: u32,
+    // Balles en reserve a l'apparition, en plus du chargeur plein
+    pub reserve_ammo: u32,
+    // Secondes pendant lesquelles on ne peut pas tirer
+    pub reload_time: f32,
+    // Secondes pour sortir l'arme apres un changement, sans pouvoir tirer
+    pub equip_time: f32,
+    pub view_model: ViewModel,
+    // Son joue a chaque tir, chemin dans `assets/`
+    pub sound: String,
+    // Clic joue quand on tire avec un chargeur vide
+    pub dry_fire_sound: String,
+}
+
+/// Facon dont les tirs d'une arme touchent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum FireMode {
+    // Balle visible qui avance a `projectile_speed`
+    Projectile,
+    // Touche tout de suite ce qui est sous le viseur et laisse une trainee
+    Hitscan,
+}
+
+/// Forme de l'arme vue a la premiere personne, pour l'instant un simple pave
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ViewModel {
+    pub size: (f32, f32, f32),
+    pub color: (f32, f32, f32),
+}
+
+impl WeaponDefinition {
+    /// Lit et verifie une arme : une valeur absurde ferait paniquer les timers ou le tirage
+    /// de la dispersion bien apres le chargement
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, WeaponLoadError> {
+        let definition: WeaponDefinition =
+            ron::de::from_bytes(bytes).map_err(WeaponLoadError::Ron)?;
+        definition.validate().map_err(WeaponLoadError::Invalid)?;
+        Ok(definition)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("fire_interval", self.fire_interval),
+            ("projectile_speed", self.projectile_speed),
+            ("projectile_lifetime", self.projectile_lifetime),
+            ("reload_time", self.reload_time),
+            ("equip_time", self.equip_time),
+        ];
+        if let Some((field, value)) = positive
+            .into_iter()
+            .find(|(_, value)| !value.is_finite() || *value <= 0.0)
+        {
+            return Err(format!("{} doit etre positif, pas {}", field, value));
+        }
+        if !self.spread.is_finite() || self.spread < 0.0 {
+            return Err(format!("spread doit etre positif ou nul, pas {}", self.spread));
+        }
+        if self.magazine_size == 0 {
+            return Err("magazine_size doit etre au moins 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum WeaponLoadError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    // Fichier lisible mais valeur inutilisable
+    Invalid(String),
+}
+
+impl fmt::Display for WeaponLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeaponLoadError::Io(error) => write!(f, "lecture de l'arme impossible : {}", error),
+            WeaponLoadError::Ron(error) => write!(f, "arme invalide : {}", error),
+            WeaponLoadError::Invalid(error) => write!(f, "arme invalide : {}", error),
+        }
+    }
+}
+
+impl std::error::Error for WeaponLoadError {}
+
+#[derive(Default)]
+pub struct WeaponLoader;
+
+impl AssetLoader for WeaponLoader {
+    type Asset = WeaponDefinition;
+    type Settings = ();
+    type Error = WeaponLoadError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<WeaponDefinition, WeaponLoadError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(WeaponLoadError::Io)?;
+        WeaponDefinition::from_ron(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+/// Armes chargees, par id. Vide tant que les fichiers ne sont pas lus :
+/// le serveur ignore alors les tirs et le client ne tire pas.
+#[derive(Debug, Default, Resource)]
+pub struct WeaponRegistry {
+    definitions: HashMap<WeaponId, WeaponDefinition>,
+}
+
+impl WeaponRegistry {
+    pub fn get(&self, id: WeaponId) -> Option<&WeaponDefinition> {
+        self.definitions.get(&id)
+    }
+
+    /// Garde la premiere definition en cas d'id en double
+    pub fn insert(&mut self, definition: WeaponDefinition) {
+        if let Some(existing) = self.definitions.get(&definition.id) {
+            println!(
+                "Weapon {} ({}) ignored, id already used by {}",
+                definition.name, definition.id, existing.name
+            );
+            return;
+        }
+        self.definitions.insert(definition.id, definition);
+    }
+}
+
+// Garde le dossier charge, sinon les armes seraient dechargees
+#[derive(Debug, Resource)]
+struct WeaponFolder(#[allow(dead_code)] Handle<LoadedFolder>);
+
+fn load_weapons(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WeaponFolder(asset_server.load_folder(WEAPONS_FOLDER)));
+}
+
+/// Reconstruit le registre a chaque arme chargee ou modifiee
+fn update_registry(
+    mut events: EventReader<AssetEvent<WeaponDefinition>>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    mut registry: ResMut<WeaponRegistry>,
+) {
+    let changes = events
+        .read()
+        .filter(|event| {
+            matches!(
+                event,
+                AssetEvent::Added { .. } | AssetEvent::Modified { .. } | AssetEvent::Removed { .. }
+            )
+        })
+        .count();
+    if changes == 0 {
+        return;
+    }
+    *registry = WeaponRegistry::default();
+    for (_, definition) in definitions.iter() {
+        registry.insert(definition.clone());
+    }
+    let mut names: Vec<&str> = registry
+        .definitions
+        .values()
+        .map(|definition| definition.name.as_str())
+        .collect();
+    names.sort();
+    println!("Weapons loaded: {}", names.join(", "));
+}
+
+/// A ajouter apres `AssetPlugin`, cote serveur et cote client
+pub struct WeaponDefinitions;
+
+impl Plugin for WeaponDefinitions {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<WeaponDefinition>();
+        app.init_asset_loader::<WeaponLoader>();
+        app.init_resource::<WeaponRegistry>();
+        app.add_systems(Startup, load_weapons);
+        app.add_systems(Update, update_registry);
+    }
+}
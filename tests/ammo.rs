@@ -0,0 +1,59 @@
+//! Chargeur et reserve, comptes pareil par le serveur et le client.
+
+use game_test::ammo::Ammo;
+
+mod common;
+
+use common::rifle;
+
+#[test]
+fn empty_magazine_cannot_fire() {
+    let mut ammo = Ammo {
+        magazine: 1,
+        reserve: 10,
+    };
+    assert!(ammo.try_fire());
+    assert!(!ammo.try_fire());
+    assert_eq!(ammo.magazine, 0);
+    assert_eq!(ammo.reserve, 10);
+}
+
+#[test]
+fn reload_takes_only_what_is_left_in_reserve() {
+    let rifle = rifle();
+    let mut ammo = Ammo::full(&rifle);
+    for _ in 0..30 {
+        assert!(ammo.try_fire());
+    }
+    ammo.reload(&rifle);
+    assert_eq!(
+        ammo,
+        Ammo {
+            magazine: 30,
+            reserve: 10
+        }
+    );
+
+    for _ in 0..25 {
+        ammo.try_fire();
+    }
+    ammo.reload(&rifle);
+    assert_eq!(
+        ammo,
+        Ammo {
+            magazine: 15,
+            reserve: 0
+        }
+    );
+}
+
+#[test]
+fn no_reload_when_full_or_out_of_reserve() {
+    let rifle = rifle();
+    let mut ammo = Ammo::full(&rifle);
+    assert!(!ammo.can_reload(&rifle));
+    ammo.try_fire();
+    assert!(ammo.can_reload(&rifle));
+    ammo.reserve = 0;
+    assert!(!ammo.can_reload(&rifle));
+}
@@ -0,0 +1,27 @@
+//! Arme de test partagee par les tests d'integration.
+
+use game_test::weapon_definition::{FireMode, ViewModel, WeaponDefinition};
+
+/// Fusil a projectiles : un tir tous les 0.1 s, balles a 50 m/s
+pub fn rifle() -> WeaponDefinition {
+    WeaponDefinition {
+        id: 0,
+        name: "Test".to_string(),
+        fire_mode: FireMode::Projectile,
+        fire_interval: 0.1,
+        projectile_speed: 50.0,
+        projectile_lifetime: 10.0,
+        damage: 25.0,
+        spread: 0.0,
+        magazine_size: 30,
+        reserve_ammo: 40,
+        reload_time: 1.5,
+        equip_time: 0.5,
+        view_model: ViewModel {
+            size: (0.1, 0.1, 0.8),
+            color: (1.0, 1.0, 1.0),
+        },
+        sound: String::new(),
+        dry_fire_sound: String::new(),
+    }
+}
@@ -0,0 +1,52 @@
+//! Le serveur refuse les tirs plus rapides que la cadence de l'arme.
+
+use game_test::player::PlayerData;
+use game_test::server::{take_shot, ServerTick};
+use renet::ClientId;
+
+mod common;
+
+use common::rifle;
+
+// Le fusil de test tire tous les 6 ticks a cette frequence
+const RATE: u32 = 60;
+
+fn tick(tick: u32) -> ServerTick {
+    ServerTick { rate: RATE, tick }
+}
+
+#[test]
+fn shots_at_the_weapon_rate_are_accepted() {
+    let rifle = rifle();
+    let mut player = PlayerData::new(ClientId::from_raw(1));
+    for shot in 0..rifle.magazine_size {
+        assert!(take_shot(&mut player, &rifle, &tick(100 + shot * 6)).is_ok());
+    }
+}
+
+#[test]
+fn a_burst_in_one_packet_is_cut_short() {
+    let rifle = rifle();
+    let mut player = PlayerData::new(ClientId::from_raw(1));
+    let accepted = (0..rifle.magazine_size)
+        .filter(|_| take_shot(&mut player, &rifle, &tick(100)).is_ok())
+        .count();
+    // Le premier tir, plus ceux que couvre l'avance laissee au client (15 ticks)
+    assert_eq!(accepted, 3);
+    // Le chargeur n'a perdu que les tirs acceptes
+    let ammo = take_shot(&mut player, &rifle, &tick(100)).unwrap_err();
+    assert_eq!(ammo.magazine, rifle.magazine_size - 3);
+}
+
+#[test]
+fn the_lead_is_not_renewed_by_each_shot() {
+    let rifle = rifle();
+    let mut player = PlayerData::new(ClientId::from_raw(1));
+    // Deux fois la cadence : l'avance s'epuise au bout de quelques tirs
+    let accepted = (0..10)
+        .filter(|shot| take_shot(&mut player, &rifle, &tick(100 + shot * 3)).is_ok())
+        .count();
+    assert!(accepted < 10);
+    // Apres une pause, la cadence normale repasse
+    assert!(take_shot(&mut player, &rifle, &tick(200)).is_ok());
+}
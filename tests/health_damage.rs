@@ -0,0 +1,54 @@
+//! Degats appliques par le serveur : armure d'abord, puis la vie, jamais en dessous de zero.
+
+use game_test::health::{self, Armor, Health, HitLocation};
+
+#[test]
+fn damage_without_armor_goes_to_health() {
+    let mut health = Health::default();
+    let mut armor = Armor {
+        points: 0.0,
+        absorption: 0.5,
+    };
+    assert_eq!(health::apply_damage(&mut health, &mut armor, 30.0), 30.0);
+    assert_eq!(health.current, 70.0);
+    assert!(!health.is_dead());
+}
+
+#[test]
+fn armor_absorbs_part_of_the_damage_until_empty() {
+    let mut health = Health::default();
+    let mut armor = Armor {
+        points: 10.0,
+        absorption: 0.5,
+    };
+    assert_eq!(health::apply_damage(&mut health, &mut armor, 16.0), 8.0);
+    assert_eq!(armor.points, 2.0);
+    // Il ne reste que 2 points d'armure a user
+    assert_eq!(health::apply_damage(&mut health, &mut armor, 16.0), 14.0);
+    assert_eq!(armor.points, 0.0);
+    assert_eq!(health.current, 78.0);
+}
+
+#[test]
+fn health_stops_at_zero() {
+    let mut health = Health {
+        current: 10.0,
+        max: 100.0,
+    };
+    let mut armor = Armor {
+        points: 0.0,
+        absorption: 0.5,
+    };
+    assert_eq!(health::apply_damage(&mut health, &mut armor, 50.0), 10.0);
+    assert_eq!(health.current, 0.0);
+    assert!(health.is_dead());
+}
+
+#[test]
+fn hit_location_depends_on_height() {
+    assert_eq!(HitLocation::from_height(0.9), HitLocation::Head);
+    assert_eq!(HitLocation::from_height(0.0), HitLocation::Body);
+    assert_eq!(HitLocation::from_height(-0.8), HitLocation::Legs);
+    assert!(HitLocation::Head.multiplier() > HitLocation::Body.multiplier());
+    assert!(HitLocation::Legs.multiplier() < HitLocation::Body.multiplier());
+}
@@ -0,0 +1,177 @@
+//! Le serveur doit verifier un tir contre les joueurs tels que le tireur les voyait.
+
+use bevy::math::Vec3;
+use game_test::hitbox::{self, Flight, FlyingProjectile, HitboxHistory, Hitboxes};
+use renet::ClientId;
+
+const SHOOTER: ClientId = ClientId::from_raw(1);
+const TARGET: ClientId = ClientId::from_raw(2);
+
+/// La cible traverse l'axe X a 1 m par tick, le tireur reste a l'origine
+fn history() -> HitboxHistory {
+    let mut history = HitboxHistory::default();
+    for tick in 1..=10 {
+        let hitboxes: Hitboxes = [
+            (SHOOTER, Vec3::new(0., 1., 0.)),
+            (TARGET, Vec3::new(tick as f32, 1., -10.)),
+        ]
+        .into_iter()
+        .collect();
+        history.push(tick, hitboxes);
+    }
+    history
+}
+
+#[test]
+fn rewind_interpolates_between_ticks() {
+    let hitboxes = history().rewind(4.5);
+    assert_eq!(hitboxes[&TARGET], Vec3::new(4.5, 1., -10.));
+    assert_eq!(hitboxes[&SHOOTER], Vec3::new(0., 1., 0.));
+}
+
+#[test]
+fn rewind_is_clamped_to_known_ticks() {
+    let history = history();
+    assert_eq!(history.rewind(-3.)[&TARGET].x, 1.);
+    assert_eq!(history.rewind(50.)[&TARGET].x, 10.);
+    assert!(HitboxHistory::default().rewind(1.).is_empty());
+}
+
+#[test]
+fn old_positions_are_forgotten() {
+    let mut history = history();
+    for tick in 11..=200 {
+        history.push(tick, Hitboxes::new());
+    }
+    assert!(history.rewind(5.).is_empty());
+}
+
+#[test]
+fn shot_hits_the_rewound_target() {
+    let history = history();
+    // Tir droit devant vers x = 3 : la cible y etait au tick 3, plus au tick 10
+    let origin = Vec3::new(3., 1., 0.);
+    let direction = Vec3::NEG_Z;
+    let hit = hitbox::ray_test(&history.rewind(3.), SHOOTER, origin, direction, 100.);
+    let (victim, distance) = hit.expect("la cible doit etre touchee");
+    assert_eq!(victim, TARGET);
+    // Rayon du collider : 0.3 + 0.2 d'arrondi
+    assert!((distance - 9.5).abs() < 0.01);
+    assert!(hitbox::ray_test(&history.rewind(10.), SHOOTER, origin, direction, 100.).is_none());
+}
+
+#[test]
+fn shooter_and_out_of_range_targets_are_ignored() {
+    let history = history();
+    let hitboxes = history.rewind(3.);
+    // Le rayon part de l'interieur du tireur
+    let origin = Vec3::new(0., 1., 0.);
+    let towards_target = (Vec3::new(3., 1., -10.) - origin).normalize();
+    assert_eq!(
+        hitbox::ray_test(&hitboxes, SHOOTER, origin, towards_target, 100.).map(|hit| hit.0),
+        Some(TARGET)
+    );
+    assert!(hitbox::ray_test(&hitboxes, SHOOTER, origin, towards_target, 5.).is_none());
+    assert!(hitbox::ray_test(&hitboxes, SHOOTER, origin, Vec3::Z, 100.).is_none());
+}
+
+#[test]
+fn closest_target_is_hit_first() {
+    let hitboxes: Hitboxes = [
+        (SHOOTER, Vec3::ZERO),
+        (TARGET, Vec3::new(0., 0., -20.)),
+        (ClientId::from_raw(3), Vec3::new(0., 0., -8.)),
+    ]
+    .into_iter()
+    .collect();
+    let hit = hitbox::ray_test(&hitboxes, SHOOTER, Vec3::ZERO, Vec3::NEG_Z, 100.);
+    assert_eq!(hit.map(|hit| hit.0), Some(ClientId::from_raw(3)));
+}
+
+#[test]
+fn hit_claim_is_confirmed_on_the_rewound_target() {
+    let history = history();
+    let origin = Vec3::new(3., 1., 0.);
+    let claim = |tick| {
+        hitbox::confirm_claim(&history.rewind(tick), SHOOTER, TARGET, origin, Vec3::NEG_Z, 100.)
+    };
+    assert!(claim(3.).is_some_and(|distance| (distance - 9.5).abs() < 0.01));
+    // La cible n'etait plus la : le tireur ment ou son horloge est fausse
+    assert!(claim(10.).is_none());
+}
+
+#[test]
+fn hit_claim_through_another_player_is_refused() {
+    let blocker = ClientId::from_raw(3);
+    let hitboxes: Hitboxes = [
+        (SHOOTER, Vec3::ZERO),
+        (TARGET, Vec3::new(0., 0., -20.)),
+        (blocker, Vec3::new(0., 0., -8.)),
+    ]
+    .into_iter()
+    .collect();
+    let claim = |victim| {
+        hitbox::confirm_claim(&hitboxes, SHOOTER, victim, Vec3::ZERO, Vec3::NEG_Z, 100.)
+    };
+    assert!(claim(TARGET).is_none());
+    assert!(claim(blocker).is_some());
+}
+
+/// Balle lente (2 m par tick) tiree au tick 3 vers -Z depuis `x`, vole jusqu'a sa fin
+fn fly(x: f32, wall: Option<f32>) -> (Flight, FlyingProjectile) {
+    let history = history();
+    let mut projectile = FlyingProjectile {
+        shooter: SHOOTER,
+        position: Vec3::new(x, 1., 0.),
+        velocity: Vec3::new(0., 0., -2.),
+        tick: 3.,
+        remaining: 10.,
+    };
+    loop {
+        let flight = projectile.advance(&history, 1., wall);
+        if flight != Flight::Flying {
+            return (flight, projectile);
+        }
+    }
+}
+
+#[test]
+fn projectile_misses_a_target_it_cannot_reach_in_time() {
+    // Un rayon instantane au tick 3 toucherait, la balle arrive quand la cible est partie
+    let (flight, _) = fly(3., None);
+    assert_eq!(flight, Flight::Stopped);
+}
+
+#[test]
+fn projectile_hits_a_target_that_walks_into_it() {
+    // La cible arrive en x = 8 au tick 8, quand la balle atteint z = -10
+    let (flight, _) = fly(8., None);
+    let Flight::Hit { victim, position, .. } = flight else {
+        panic!("la cible doit etre touchee, pas {:?}", flight);
+    };
+    assert_eq!(victim, TARGET);
+    // Dans le collider de la cible
+    assert!((-10.5..=-9.5).contains(&position.z));
+}
+
+#[test]
+fn projectile_stops_on_a_wall_before_the_target() {
+    let (flight, projectile) = fly(8., Some(1.));
+    assert_eq!(flight, Flight::Stopped);
+    assert_eq!(projectile.position.z, 0.);
+}
+
+#[test]
+fn projectile_expires_at_the_end_of_its_lifetime() {
+    let history = history();
+    let mut projectile = FlyingProjectile {
+        shooter: SHOOTER,
+        position: Vec3::new(-50., 1., 0.),
+        velocity: Vec3::NEG_Z,
+        tick: 1.,
+        remaining: 3.,
+    };
+    assert_eq!(projectile.advance(&history, 1., None), Flight::Flying);
+    assert_eq!(projectile.advance(&history, 1., None), Flight::Flying);
+    assert_eq!(projectile.advance(&history, 1., None), Flight::Stopped);
+}
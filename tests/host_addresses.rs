@@ -0,0 +1,47 @@
+//! Les adresses de la partie hebergee ne touchent pas au serveur a rejoindre.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use game_test::connection::{ConnectionSettings, DEFAULT_PORT};
+
+fn settings() -> ConnectionSettings {
+    ConnectionSettings::from_args(["--connect".to_string(), "10.0.0.2:6000".to_string()]).unwrap()
+}
+
+#[test]
+fn host_addresses_leave_the_join_target_alone() {
+    let mut settings = settings();
+    settings
+        .set_host_addresses("0.0.0.0", "203.0.113.7", "7000")
+        .unwrap();
+    assert_eq!(settings.server_address, "10.0.0.2:6000");
+    assert_eq!(settings.port, 7000);
+    assert_eq!(
+        settings.bind_address,
+        Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    );
+    assert_eq!(
+        settings.public_address,
+        Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)))
+    );
+}
+
+#[test]
+fn empty_fields_fall_back_to_defaults() {
+    let mut settings = settings();
+    settings.set_host_addresses("", " ", "").unwrap();
+    assert_eq!(settings.bind_address, None);
+    assert_eq!(settings.public_address, None);
+    assert_eq!(settings.port, DEFAULT_PORT);
+}
+
+#[test]
+fn invalid_fields_change_nothing() {
+    let mut settings = settings();
+    assert!(settings.set_host_addresses("::1", "", "70000").is_err());
+    assert!(settings
+        .set_host_addresses("not an ip", "", "7000")
+        .is_err());
+    assert_eq!(settings.bind_address, None);
+    assert_eq!(settings.port, DEFAULT_PORT);
+}
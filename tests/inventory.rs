@@ -0,0 +1,37 @@
+//! Emplacements d'armes du joueur local.
+
+use game_test::inventory::Inventory;
+
+fn inventory() -> Inventory {
+    Inventory {
+        slots: vec![0, 1, 2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn wheel_wraps_around_the_slots() {
+    let mut inventory = inventory();
+    assert_eq!(inventory.cycle(1), 1);
+    assert_eq!(inventory.cycle(-1), 2);
+    inventory.current = 2;
+    assert_eq!(inventory.cycle(1), 0);
+}
+
+#[test]
+fn switching_takes_the_equip_time() {
+    let mut inventory = inventory();
+    assert!(inventory.select(2, 0.5));
+    assert_eq!(inventory.weapon(), 2);
+    assert!(inventory.is_switching());
+    assert_eq!(inventory.switch_progress(), Some(0.0));
+}
+
+#[test]
+fn selecting_the_current_or_a_missing_slot_does_nothing() {
+    let mut inventory = inventory();
+    assert!(!inventory.select(0, 0.5));
+    assert!(!inventory.select(7, 0.5));
+    assert_eq!(inventory.weapon(), 0);
+    assert!(!inventory.is_switching());
+}
@@ -0,0 +1,51 @@
+//! Kills, morts et assists comptes par le serveur dans le `Lobby`.
+
+use game_test::player::{PlayerData, PlayerStats};
+use game_test::server::Lobby;
+use renet::ClientId;
+
+const A: ClientId = ClientId::from_raw(1);
+const B: ClientId = ClientId::from_raw(2);
+const C: ClientId = ClientId::from_raw(3);
+
+fn lobby() -> Lobby {
+    let mut lobby = Lobby::default();
+    for id in [A, B, C] {
+        lobby.players.insert(id, PlayerData::new(id));
+    }
+    lobby
+}
+
+fn stats(lobby: &Lobby, id: ClientId) -> PlayerStats {
+    lobby.players[&id].stats
+}
+
+#[test]
+fn kill_goes_to_the_killer_and_assist_to_the_others() {
+    let mut lobby = lobby();
+    lobby.players.get_mut(&C).unwrap().damaged_by = vec![B, A];
+    lobby.credit_kill(A, C);
+    assert_eq!(stats(&lobby, A), PlayerStats { kills: 1, deaths: 0, assists: 0 });
+    assert_eq!(stats(&lobby, B), PlayerStats { kills: 0, deaths: 0, assists: 1 });
+    assert_eq!(stats(&lobby, C), PlayerStats { kills: 0, deaths: 1, assists: 0 });
+    // Une nouvelle vie repart sans personne a qui donner d'assist
+    assert!(lobby.players[&C].damaged_by.is_empty());
+}
+
+#[test]
+fn suicide_counts_only_a_death() {
+    let mut lobby = lobby();
+    lobby.players.get_mut(&A).unwrap().damaged_by = vec![B];
+    lobby.credit_kill(A, A);
+    assert_eq!(stats(&lobby, A), PlayerStats { kills: 0, deaths: 1, assists: 0 });
+    assert_eq!(stats(&lobby, B).assists, 1);
+}
+
+#[test]
+fn disconnected_killer_is_ignored() {
+    let mut lobby = lobby();
+    lobby.players.remove(&A);
+    lobby.credit_kill(A, B);
+    assert_eq!(stats(&lobby, B).deaths, 1);
+    assert_eq!(stats(&lobby, C), PlayerStats::default());
+}
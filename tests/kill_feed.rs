@@ -0,0 +1,16 @@
+//! Fondu des lignes du kill feed.
+
+use game_test::kill_feed::{alpha, KILL_FEED_SECONDS};
+
+#[test]
+fn new_rows_are_fully_visible() {
+    assert_eq!(alpha(0.0), 1.0);
+    assert_eq!(alpha(KILL_FEED_SECONDS - 1.0), 1.0);
+}
+
+#[test]
+fn rows_fade_out_during_the_last_second() {
+    assert!((alpha(KILL_FEED_SECONDS - 0.25) - 0.25).abs() < 1e-6);
+    assert_eq!(alpha(KILL_FEED_SECONDS), 0.0);
+    assert_eq!(alpha(KILL_FEED_SECONDS + 3.0), 0.0);
+}
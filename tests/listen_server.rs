@@ -0,0 +1,47 @@
+//! Le serveur heberge par le client libere ses ports quand on l'arrete.
+
+use std::net::{TcpListener, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use game_test::connection::ConnectionSettings;
+use game_test::server;
+
+/// Le port se libere quand la boucle du serveur a vu la demande d'arret
+fn wait_until<T>(mut bind: impl FnMut() -> std::io::Result<T>) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if bind().is_ok() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn shutdown_frees_the_game_and_token_ports() {
+    let settings = ConnectionSettings::from_args(
+        [
+            "--bind",
+            "127.0.0.1",
+            "--port",
+            "5731",
+            "--secure",
+            "--password",
+            "hunter2",
+        ]
+        .map(String::from),
+    )
+    .unwrap();
+    let listen_server = server::spawn_listen_server(&settings).unwrap();
+    // Les deux ports sont pris tant que le serveur tourne
+    assert!(UdpSocket::bind(settings.server_bind_addr()).is_err());
+    assert!(TcpListener::bind(settings.server_bind_addr()).is_err());
+
+    listen_server.shutdown();
+    assert!(wait_until(|| UdpSocket::bind(settings.server_bind_addr())));
+    assert!(wait_until(|| TcpListener::bind(
+        settings.server_bind_addr()
+    )));
+}
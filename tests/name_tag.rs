@@ -0,0 +1,37 @@
+//! Couleur et opacite des noms affiches au dessus des joueurs distants.
+
+use game_test::name_tag::fade;
+use game_test::player::{Affiliation, Relationship};
+
+fn affiliation(team: Option<u8>, squad: Option<u8>) -> Affiliation {
+    Affiliation { team, squad }
+}
+
+#[test]
+fn players_without_team_are_enemies() {
+    let nobody = Affiliation::default();
+    assert_eq!(Relationship::between(&nobody, &nobody), Relationship::Enemy);
+    let team = affiliation(Some(1), None);
+    assert_eq!(Relationship::between(&team, &nobody), Relationship::Enemy);
+}
+
+#[test]
+fn same_team_then_same_squad() {
+    let local = affiliation(Some(1), Some(2));
+    assert_eq!(Relationship::between(&local, &affiliation(Some(2), Some(2))), Relationship::Enemy);
+    assert_eq!(Relationship::between(&local, &affiliation(Some(1), Some(3))), Relationship::Teammate);
+    assert_eq!(Relationship::between(&local, &affiliation(Some(1), None)), Relationship::Teammate);
+    assert_eq!(Relationship::between(&local, &affiliation(Some(1), Some(2))), Relationship::Squad);
+    // Sans escouade, personne n'est dans la notre
+    let local = affiliation(Some(1), None);
+    assert_eq!(Relationship::between(&local, &affiliation(Some(1), None)), Relationship::Teammate);
+}
+
+#[test]
+fn names_fade_with_distance() {
+    assert_eq!(fade(5.0), 1.0);
+    assert_eq!(fade(20.0), 1.0);
+    assert!((fade(30.0) - 0.5).abs() < 1e-6);
+    assert_eq!(fade(40.0), 0.0);
+    assert_eq!(fade(100.0), 0.0);
+}
@@ -0,0 +1,114 @@
+//! Commandes du joueur : bornees par le serveur, repetees par le client jusqu'a confirmation.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use game_test::player::{InputAck, InputHistory, PlayerData, PlayerInput, SPRINT_MULTIPLIER};
+use game_test::server::receive_inputs;
+use renet::ClientId;
+
+#[test]
+fn honest_input_is_kept() {
+    let input = PlayerInput {
+        movement: Vec3::new(0., 1., -SPRINT_MULTIPLIER),
+        yaw: 1.0,
+        pitch: -0.5,
+    };
+    assert_eq!(input.sanitized(), Some(input));
+}
+
+#[test]
+fn speed_and_jump_are_capped() {
+    let input = PlayerInput {
+        movement: Vec3::new(300., 50., 400.),
+        yaw: 0.,
+        pitch: 0.,
+    };
+    let sanitized = input.sanitized().unwrap();
+    let horizontal = Vec2::new(sanitized.movement.x, sanitized.movement.z);
+    assert!((horizontal.length() - SPRINT_MULTIPLIER).abs() < 1e-5);
+    // La direction est gardee
+    assert!((horizontal.normalize() - Vec2::new(0.6, 0.8)).length() < 1e-5);
+    assert_eq!(sanitized.movement.y, 1.0);
+}
+
+#[test]
+fn look_is_wrapped_and_clamped() {
+    let input = PlayerInput {
+        movement: Vec3::ZERO,
+        yaw: 5.0 * PI,
+        pitch: 10.0,
+    };
+    let sanitized = input.sanitized().unwrap();
+    assert!((-PI..=PI).contains(&sanitized.yaw));
+    assert!((sanitized.yaw.abs() - PI).abs() < 1e-4);
+    assert_eq!(sanitized.pitch, FRAC_PI_2);
+}
+
+#[test]
+fn non_finite_input_is_rejected() {
+    for input in [
+        PlayerInput {
+            movement: Vec3::new(f32::NAN, 0., 0.),
+            ..default()
+        },
+        PlayerInput {
+            yaw: f32::INFINITY,
+            ..default()
+        },
+        PlayerInput {
+            pitch: f32::NEG_INFINITY,
+            ..default()
+        },
+    ] {
+        assert_eq!(input.sanitized(), None);
+    }
+}
+
+#[test]
+fn unacknowledged_inputs_are_repeated_until_acked() {
+    let mut history = InputHistory::default();
+    for _ in 0..20 {
+        history.push(PlayerInput::default());
+    }
+    let sequences = |history: &InputHistory| -> Vec<u32> {
+        history
+            .to_send(16)
+            .iter()
+            .map(|record| record.sequence)
+            .collect()
+    };
+    // Seules les 16 plus recentes partent
+    assert_eq!(sequences(&history), (5..=20).collect::<Vec<_>>());
+    history.receive_ack(InputAck {
+        sequence: 18,
+        ..default()
+    });
+    assert_eq!(sequences(&history), vec![19, 20]);
+}
+
+#[test]
+fn repeated_inputs_are_queued_once() {
+    let mut player = PlayerData::new(ClientId::from_raw(1));
+    receive_inputs(&mut player, 2, vec![PlayerInput::default(); 2]);
+    receive_inputs(&mut player, 3, vec![PlayerInput::default(); 3]);
+    let sequences: Vec<u32> = player
+        .pending_inputs
+        .iter()
+        .map(|(sequence, _)| *sequence)
+        .collect();
+    assert_eq!(sequences, [1, 2, 3]);
+}
+
+#[test]
+fn last_sequence_number_does_not_overflow() {
+    let mut player = PlayerData::new(ClientId::from_raw(1));
+    receive_inputs(&mut player, u32::MAX, vec![PlayerInput::default(); 3]);
+    receive_inputs(&mut player, u32::MAX - 1, vec![PlayerInput::default(); 2]);
+    let sequences: Vec<u32> = player
+        .pending_inputs
+        .iter()
+        .map(|(sequence, _)| *sequence)
+        .collect();
+    assert_eq!(sequences, [u32::MAX - 2, u32::MAX - 1]);
+}
@@ -0,0 +1,159 @@
+//! Une balle rapide ne doit pas traverser un mur fin, meme a faible frequence d'images.
+
+use std::time::Duration;
+
+use bevy::{
+    asset::{AssetApp, AssetPlugin},
+    prelude::*,
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+};
+use bevy_rapier3d::prelude::{Collider, NoUserData, RapierContext, RapierPhysicsPlugin, RigidBody};
+use game_test::player::player_collider;
+use game_test::projectile::{self, Projectile};
+use renet::ClientId;
+
+mod common;
+
+use common::rifle;
+
+// Vitesse des balles du fusil de test
+const SPEED: f32 = 50.0;
+// 10 m par frame : bien plus que l'epaisseur du mur
+const STEP: Duration = Duration::from_millis(200);
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+        ScenePlugin,
+    ))
+    // Rapier attend les assets de mesh meme sans rendu
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+    .insert_resource(TimeUpdateStrategy::ManualDuration(STEP))
+    .add_systems(Update, projectile::update_projectiles);
+    app
+}
+
+/// Mur de 0.2 d'epaisseur, a 5 m devant l'origine
+fn spawn_wall(app: &mut App) {
+    app.world_mut().spawn((
+        TransformBundle::from_transform(Transform::from_xyz(0., 0., -5.)),
+        Collider::cuboid(5., 5., 0.1),
+        RigidBody::Fixed,
+    ));
+}
+
+/// Balle tiree depuis l'origine vers -Z, avec les composants du vrai tir
+fn spawn_projectile(app: &mut App) -> Entity {
+    let projectile = Projectile {
+        owner: ClientId::from_raw(1),
+        weapon: 0,
+        shot: 1,
+        network_id: None,
+    };
+    let world = app.world_mut();
+    world.resource_scope(|world, mut meshes: Mut<Assets<Mesh>>| {
+        world.resource_scope(|world, mut materials: Mut<Assets<StandardMaterial>>| {
+            let bundle = projectile::projectile_bundle(
+                &mut meshes,
+                &mut materials,
+                projectile,
+                Vec3::ZERO,
+                Vec3::NEG_Z,
+                &rifle(),
+            );
+            world.spawn(bundle).id()
+        })
+    })
+}
+
+/// Laisse Rapier enregistrer les colliders avant le tir
+fn fire(app: &mut App) -> Entity {
+    app.update();
+    app.update();
+    spawn_projectile(app)
+}
+
+#[test]
+fn projectile_stops_in_a_thin_wall_with_a_large_timestep() {
+    let mut app = app();
+    spawn_wall(&mut app);
+    let bullet = fire(&mut app);
+    for _ in 0..3 {
+        app.update();
+    }
+    assert!(app.world().get_entity(bullet).is_none());
+}
+
+#[test]
+fn sweep_reports_the_wall_surface() {
+    let mut app = app();
+    spawn_wall(&mut app);
+    fire(&mut app);
+    let context = app.world().resource::<RapierContext>();
+    let velocity = Vec3::NEG_Z * SPEED;
+    let (_, time_of_impact) =
+        projectile::sweep(context, Vec3::ZERO, velocity, 0.2).expect("le mur doit etre touche");
+    // Face avant du mur moins le rayon de la balle
+    assert!((time_of_impact * SPEED - 4.89).abs() < 0.01);
+    assert!(projectile::sweep(context, Vec3::ZERO, velocity, 0.05).is_none());
+}
+
+#[test]
+fn projectile_stops_in_a_player() {
+    let mut app = app();
+    app.world_mut().spawn((
+        TransformBundle::from_transform(Transform::from_xyz(0., 0., -5.)),
+        player_collider(),
+        RigidBody::KinematicPositionBased,
+    ));
+    let bullet = fire(&mut app);
+    for _ in 0..3 {
+        app.update();
+    }
+    assert!(app.world().get_entity(bullet).is_none());
+}
+
+#[test]
+fn projectile_leaves_the_shooter_and_flies_on_without_obstacle() {
+    let mut app = app();
+    // Le tireur englobe le point de depart : il ne doit pas arreter sa propre balle
+    app.world_mut().spawn((
+        TransformBundle::default(),
+        player_collider(),
+        RigidBody::KinematicPositionBased,
+    ));
+    let bullet = fire(&mut app);
+    for _ in 0..3 {
+        app.update();
+    }
+    let transform = app
+        .world()
+        .get::<Transform>(bullet)
+        .expect("la balle vole encore");
+    assert!(transform.translation.z < -20.);
+}
+
+#[test]
+fn projectiles_do_not_stop_each_other() {
+    let mut app = app();
+    // Deux balles parties ensemble du meme point
+    let first = fire(&mut app);
+    let second = spawn_projectile(&mut app);
+    for _ in 0..3 {
+        app.update();
+    }
+    for bullet in [first, second] {
+        let transform = app
+            .world()
+            .get::<Transform>(bullet)
+            .expect("la balle vole encore");
+        assert!(transform.translation.z < -20.);
+    }
+}
@@ -0,0 +1,149 @@
+//! Le decodeur recoit des octets venant du reseau : il doit rendre une erreur, jamais paniquer.
+
+use bevy::math::Vec3;
+use game_test::client::ClientMessages;
+use game_test::player::PlayerInput;
+use game_test::protocol::{self, Channel, DecodeError, MAX_MESSAGE_SIZE};
+use game_test::server::ServerMessages;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use renet::ClientId;
+
+fn valid_messages() -> Vec<Vec<u8>> {
+    vec![
+        protocol::encode(&ClientMessages::PlayerInput {
+            sequence: 42,
+            inputs: vec![
+                PlayerInput::default(),
+                PlayerInput {
+                    movement: Vec3::new(0., 0., -1.),
+                    yaw: 1.5,
+                    pitch: -0.2,
+                },
+            ],
+        }),
+        protocol::encode(&ClientMessages::ProjectileSpawned {
+            shot: 3,
+            weapon: 0,
+            position: Vec3::new(1., 2., 3.),
+            direction: Vec3::Z,
+            view_time: 12.5,
+        }),
+        protocol::encode(&ClientMessages::TestMessage {
+            message: "hello".to_string(),
+        }),
+        protocol::encode(&ClientMessages::SnapshotAck { tick: 1234 }),
+        protocol::encode(&ClientMessages::Reload { weapon: 0 }),
+        protocol::encode(&ClientMessages::SwitchWeapon { weapon: 1 }),
+        protocol::encode(&ClientMessages::HitscanFired {
+            weapon: 1,
+            origin: Vec3::new(1., 2., 3.),
+            direction: Vec3::NEG_Z,
+            distance: 14.2,
+            view_time: 12.5,
+            victim: Some(ClientId::from_raw(7)),
+        }),
+    ]
+}
+
+fn decode_both(bytes: &[u8]) {
+    let _ = protocol::decode::<ClientMessages>(bytes);
+    let _ = protocol::decode::<ServerMessages>(bytes);
+}
+
+#[test]
+fn random_bytes_never_panic() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    for _ in 0..20_000 {
+        let len = rng.gen_range(0..256);
+        let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        decode_both(&bytes);
+    }
+}
+
+#[test]
+fn corrupted_messages_never_panic() {
+    let mut rng = StdRng::seed_from_u64(0xbad);
+    for message in valid_messages() {
+        for _ in 0..2_000 {
+            let mut bytes = message.clone();
+            for _ in 0..rng.gen_range(1..4) {
+                let index = rng.gen_range(0..bytes.len());
+                bytes[index] = rng.gen();
+            }
+            decode_both(&bytes);
+        }
+    }
+}
+
+#[test]
+fn truncated_messages_are_rejected() {
+    for message in valid_messages() {
+        for len in 0..message.len() {
+            assert!(protocol::decode::<ClientMessages>(&message[..len]).is_err());
+        }
+    }
+}
+
+#[test]
+fn valid_messages_round_trip() {
+    for message in valid_messages() {
+        let decoded: ClientMessages = protocol::decode(&message).unwrap();
+        assert_eq!(protocol::encode(&decoded), message);
+    }
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+    for mut message in valid_messages() {
+        message.push(0);
+        assert!(protocol::decode::<ClientMessages>(&message).is_err());
+    }
+}
+
+#[test]
+fn oversized_messages_are_rejected() {
+    let bytes = vec![0; MAX_MESSAGE_SIZE + 1];
+    assert!(matches!(
+        protocol::decode::<ClientMessages>(&bytes),
+        Err(DecodeError::TooLarge(_))
+    ));
+}
+
+#[test]
+fn huge_length_prefix_is_rejected_without_allocating() {
+    // TestMessage (variante 2) avec une chaine annoncee de u64::MAX octets
+    let mut bytes = vec![2, 0xfd];
+    bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        protocol::decode::<ClientMessages>(&bytes),
+        Err(DecodeError::Malformed(_))
+    ));
+}
+
+#[test]
+fn weapon_commands_and_shots_share_an_ordered_channel() {
+    let shot = ClientMessages::ProjectileSpawned {
+        shot: 1,
+        weapon: 1,
+        position: Vec3::ZERO,
+        direction: Vec3::Z,
+        view_time: 0.0,
+    };
+    let hitscan = ClientMessages::HitscanFired {
+        weapon: 1,
+        origin: Vec3::ZERO,
+        direction: Vec3::Z,
+        distance: 10.0,
+        view_time: 0.0,
+        victim: None,
+    };
+    // Un changement d'arme suivi d'un tir doit arriver dans cet ordre
+    for message in [
+        ClientMessages::SwitchWeapon { weapon: 1 },
+        ClientMessages::Reload { weapon: 1 },
+        hitscan,
+    ] {
+        assert_eq!(message.channel(), shot.channel());
+    }
+    assert_eq!(shot.channel(), Channel::Actions);
+}
@@ -0,0 +1,45 @@
+//! Tableau des scores construit par le serveur a partir du `Lobby`.
+
+use game_test::player::{PlayerData, PlayerStats};
+use game_test::server::Lobby;
+use renet::ClientId;
+
+const A: ClientId = ClientId::from_raw(1);
+const B: ClientId = ClientId::from_raw(2);
+const C: ClientId = ClientId::from_raw(3);
+
+fn player(id: ClientId, kills: u32, deaths: u32, assists: u32) -> PlayerData {
+    let mut player = PlayerData::new(id);
+    player.stats = PlayerStats { kills, deaths, assists };
+    player
+}
+
+#[test]
+fn entries_are_sorted_by_score_then_kills() {
+    let mut lobby = Lobby::default();
+    // B : 2 assists valent 1 kill, mais A a plus de kills a score egal
+    for player in [player(B, 0, 0, 2), player(C, 0, 3, 0), player(A, 1, 5, 0)] {
+        lobby.players.insert(player.id, player);
+    }
+    let ids: Vec<ClientId> = lobby.scoreboard().iter().map(|entry| entry.id).collect();
+    assert_eq!(ids, vec![A, B, C]);
+}
+
+#[test]
+fn score_and_kd_ratio() {
+    let stats = PlayerStats { kills: 3, deaths: 2, assists: 1 };
+    assert_eq!(stats.score(), 350);
+    assert_eq!(stats.kd_ratio(), 1.5);
+    // Pas de division par zero avant la premiere mort
+    let stats = PlayerStats { kills: 4, deaths: 0, assists: 0 };
+    assert_eq!(stats.kd_ratio(), 4.0);
+}
+
+#[test]
+fn ping_is_copied_from_the_lobby() {
+    let mut lobby = Lobby::default();
+    let mut a = PlayerData::new(A);
+    a.ping = 42;
+    lobby.players.insert(A, a);
+    assert_eq!(lobby.scoreboard()[0].ping, 42);
+}
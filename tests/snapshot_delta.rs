@@ -0,0 +1,144 @@
+//! Un snapshot delta applique sur sa reference doit redonner exactement l'etat du serveur.
+
+use bevy::math::Vec3;
+use game_test::player::InputAck;
+use game_test::protocol;
+use game_test::server::ServerMessages;
+use game_test::snapshot::{self, PlayerDelta, QuantizedPlayer, WorldState};
+use renet::ClientId;
+
+fn world(players: &[(u64, Vec3, f32)]) -> WorldState {
+    players
+        .iter()
+        .map(|(id, position, yaw)| (ClientId::from_raw(*id), QuantizedPlayer::new(*position, *yaw)))
+        .collect()
+}
+
+fn without(state: &WorldState, skip: u64) -> WorldState {
+    let mut state = state.clone();
+    state.remove(&ClientId::from_raw(skip));
+    state
+}
+
+#[test]
+fn delta_applied_on_baseline_gives_current_state() {
+    let baseline = world(&[
+        (1, Vec3::new(0., 1., 0.), 0.),
+        (2, Vec3::new(5., 1., -3.), 1.),
+        (3, Vec3::new(-2., 1., 8.), 2.),
+    ]);
+    let current = world(&[
+        (1, Vec3::new(0.5, 1., 0.), 0.),
+        (2, Vec3::new(5., 1., -3.), 1.),
+        (3, Vec3::new(-2., 2.5, 7.), 2.5),
+    ]);
+    let (removed, changed) = snapshot::diff(&baseline, &current, ClientId::from_raw(0));
+    assert!(removed.is_empty());
+    // Le joueur 2 n'a pas bouge
+    assert_eq!(changed.iter().map(|delta| delta.id.raw()).collect::<Vec<_>>(), [1, 3]);
+    assert_eq!(snapshot::apply(&baseline, &removed, &changed), current);
+}
+
+#[test]
+fn left_and_joined_players() {
+    let baseline = world(&[(1, Vec3::ZERO, 0.), (2, Vec3::ONE, 0.)]);
+    // Le joueur 3 arrive pile a l'origine : il doit quand meme etre envoye
+    let current = world(&[(1, Vec3::ZERO, 0.), (3, Vec3::ZERO, 0.)]);
+    let (removed, changed) = snapshot::diff(&baseline, &current, ClientId::from_raw(0));
+    assert_eq!(removed, [ClientId::from_raw(2)]);
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0].id, ClientId::from_raw(3));
+    assert_eq!(snapshot::apply(&baseline, &removed, &changed), current);
+}
+
+#[test]
+fn empty_baseline_is_a_full_snapshot() {
+    let current = world(&[(1, Vec3::new(1., 2., 3.), 0.5), (2, Vec3::ZERO, 0.)]);
+    let (removed, changed) = snapshot::diff(&WorldState::new(), &current, ClientId::from_raw(0));
+    assert!(removed.is_empty());
+    assert_eq!(changed.len(), 2);
+    assert_eq!(snapshot::apply(&WorldState::new(), &removed, &changed), current);
+}
+
+#[test]
+fn receiver_is_skipped() {
+    let baseline = world(&[(1, Vec3::ZERO, 0.), (2, Vec3::ZERO, 0.)]);
+    let current = world(&[(1, Vec3::ONE, 0.)]);
+    let (removed, changed) = snapshot::diff(&baseline, &current, ClientId::from_raw(2));
+    assert!(removed.is_empty());
+    assert!(changed.iter().all(|delta| delta.id.raw() != 2));
+    let (removed, changed) = snapshot::diff(&baseline, &current, ClientId::from_raw(1));
+    assert_eq!(removed, [ClientId::from_raw(2)]);
+    assert!(changed.is_empty());
+    assert_eq!(
+        snapshot::apply(&without(&baseline, 1), &removed, &changed),
+        without(&current, 1)
+    );
+}
+
+#[test]
+fn yaw_wraps_around_the_circle() {
+    let baseline = world(&[(1, Vec3::ZERO, 6.2)]);
+    let current = world(&[(1, Vec3::ZERO, 0.1)]);
+    let (removed, changed) = snapshot::diff(&baseline, &current, ClientId::from_raw(0));
+    // Petit pas en avant, pas un demi-tour en arriere
+    assert!(changed[0].values[3] > 0 && changed[0].values[3] < 2000);
+    assert_eq!(snapshot::apply(&baseline, &removed, &changed), current);
+}
+
+#[test]
+fn quantization_keeps_positions_close() {
+    let position = Vec3::new(12.345, -0.5, 1000.001);
+    let player = QuantizedPlayer::new(position, 3.);
+    assert!(player.position().distance(position) < 0.01);
+    assert!((player.yaw() - 3.).abs() < 0.001);
+}
+
+#[test]
+fn only_changed_fields_are_sent() {
+    let baseline = world(&[(1, Vec3::ZERO, 0.)]);
+    let moved_x = world(&[(1, Vec3::new(1., 0., 0.), 0.)]);
+    let moved_all = world(&[(1, Vec3::ONE, 1.)]);
+    let (_, one_field) = snapshot::diff(&baseline, &moved_x, ClientId::from_raw(0));
+    let (_, all_fields) = snapshot::diff(&baseline, &moved_all, ClientId::from_raw(0));
+    let one_field_bytes = protocol::encode(&one_field[0]);
+    let all_fields_bytes = protocol::encode(&all_fields[0]);
+    assert!(one_field_bytes.len() < all_fields_bytes.len());
+    assert_eq!(protocol::decode::<PlayerDelta>(&one_field_bytes).unwrap(), one_field[0]);
+    assert_eq!(protocol::decode::<PlayerDelta>(&all_fields_bytes).unwrap(), all_fields[0]);
+}
+
+#[test]
+fn world_snapshot_round_trips() {
+    let baseline = world(&[(1, Vec3::ZERO, 0.), (2, Vec3::ONE, 0.)]);
+    let current = world(&[(1, Vec3::new(0., 0., 0.25), 0.), (3, Vec3::X, 1.)]);
+    let (removed, players) = snapshot::diff(&baseline, &current, ClientId::from_raw(0));
+    let bytes = protocol::encode(&ServerMessages::WorldSnapshot {
+        tick: 10,
+        baseline: 7,
+        ack: InputAck::default(),
+        removed,
+        players,
+    });
+    let Ok(ServerMessages::WorldSnapshot {
+        removed, players, ..
+    }) = protocol::decode(&bytes)
+    else {
+        panic!("WorldSnapshot attendu");
+    };
+    assert_eq!(snapshot::apply(&baseline, &removed, &players), current);
+}
+
+#[test]
+fn unknown_mask_bits_are_rejected() {
+    // 2 elements : id 1, masque avec un bit qui ne correspond a aucun champ
+    assert!(protocol::decode::<PlayerDelta>(&[2, 1, 0x10]).is_err());
+}
+
+#[test]
+fn length_must_match_the_mask() {
+    // Masque x + y mais un seul champ annonce, puis trois
+    assert!(protocol::decode::<PlayerDelta>(&[3, 1, 0b11, 10]).is_err());
+    assert!(protocol::decode::<PlayerDelta>(&[5, 1, 0b11, 10, 10, 10]).is_err());
+    assert!(protocol::decode::<PlayerDelta>(&[4, 1, 0b11, 10, 10]).is_ok());
+}
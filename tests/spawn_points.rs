@@ -0,0 +1,41 @@
+//! Le serveur fait reapparaitre les joueurs loin de leurs ennemis.
+
+use bevy::math::Vec3;
+use game_test::map::SpawnPoints;
+
+fn spawn_points() -> SpawnPoints {
+    SpawnPoints {
+        points: vec![
+            Vec3::new(-40., 1.3, -40.),
+            Vec3::new(0., 1.3, 0.),
+            Vec3::new(40., 1.3, 40.),
+        ],
+    }
+}
+
+#[test]
+fn farthest_point_from_the_nearest_enemy_is_chosen() {
+    let points = spawn_points();
+    assert_eq!(points.choose(&[Vec3::new(-35., 1.3, -35.)]), Vec3::new(40., 1.3, 40.));
+    assert_eq!(points.choose(&[Vec3::new(38., 1.3, 41.)]), Vec3::new(-40., 1.3, -40.));
+    // Un ennemi dans chaque coin : le centre est le plus loin des deux
+    assert_eq!(
+        points.choose(&[Vec3::new(-40., 1.3, -40.), Vec3::new(40., 1.3, 40.)]),
+        Vec3::new(0., 1.3, 0.)
+    );
+}
+
+#[test]
+fn any_point_without_enemies() {
+    let points = spawn_points();
+    for _ in 0..20 {
+        assert!(points.points.contains(&points.choose(&[])));
+    }
+}
+
+#[test]
+fn map_without_spawn_points_falls_back_to_the_center() {
+    let points = SpawnPoints::default();
+    assert_eq!(points.choose(&[]).x, 0.);
+    assert_eq!(points.choose(&[Vec3::ONE]).z, 0.);
+}
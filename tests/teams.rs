@@ -0,0 +1,111 @@
+//! Repartition des joueurs en equipes et en escouades a la connexion.
+
+use bevy::math::Vec3;
+use game_test::connection::ConnectionSettings;
+use game_test::player::{Affiliation, PlayerData};
+use game_test::server::{Lobby, SQUAD_SIZE};
+use renet::ClientId;
+use std::ops::Range;
+
+/// Connecte les joueurs `ids` comme le fait le serveur, un par un
+fn join(lobby: &mut Lobby, ids: Range<u64>) {
+    for id in ids {
+        let mut player = PlayerData::new(ClientId::from_raw(id));
+        player.affiliation = lobby.assign_affiliation();
+        lobby.players.insert(player.id, player);
+    }
+}
+
+fn affiliation(lobby: &Lobby, id: u64) -> Affiliation {
+    lobby.players[&ClientId::from_raw(id)].affiliation
+}
+
+#[test]
+fn free_for_all_has_no_team() {
+    let mut lobby = Lobby::default();
+    join(&mut lobby, 0..3);
+    for id in 0..3 {
+        assert_eq!(affiliation(&lobby, id), Affiliation::default());
+    }
+}
+
+#[test]
+fn teams_are_filled_in_turn() {
+    let mut lobby = Lobby {
+        teams: 2,
+        ..Default::default()
+    };
+    join(&mut lobby, 0..4);
+    let teams: Vec<_> = (0..4).map(|id| affiliation(&lobby, id).team).collect();
+    assert_eq!(teams, [Some(0), Some(1), Some(0), Some(1)]);
+}
+
+#[test]
+fn squads_are_filled_before_opening_a_new_one() {
+    let mut lobby = Lobby {
+        teams: 2,
+        ..Default::default()
+    };
+    join(&mut lobby, 0..2 * SQUAD_SIZE as u64 + 2);
+    let squads_of_team = |team| {
+        lobby
+            .players
+            .values()
+            .filter(|player| player.affiliation.team == Some(team))
+            .filter(|player| player.affiliation.squad == Some(0))
+            .count()
+    };
+    assert_eq!(squads_of_team(0), SQUAD_SIZE);
+    assert_eq!(squads_of_team(1), SQUAD_SIZE);
+    let last = 2 * SQUAD_SIZE as u64 + 1;
+    assert_eq!(affiliation(&lobby, last).squad, Some(1));
+}
+
+#[test]
+fn a_leaver_frees_a_place_in_the_smallest_team() {
+    let mut lobby = Lobby {
+        teams: 2,
+        ..Default::default()
+    };
+    join(&mut lobby, 0..4);
+    // Le joueur 2 etait dans l'equipe 0, qui a maintenant un joueur de moins
+    lobby.players.remove(&ClientId::from_raw(2));
+    join(&mut lobby, 4..5);
+    let newcomer = Affiliation {
+        team: Some(0),
+        squad: Some(0),
+    };
+    assert_eq!(affiliation(&lobby, 4), newcomer);
+}
+
+#[test]
+fn teams_option_is_bounded() {
+    let teams = |value: &str| {
+        ConnectionSettings::from_args(["--teams".to_string(), value.to_string()])
+            .map(|settings| settings.teams)
+    };
+    assert_eq!(teams("2"), Ok(2));
+    assert_eq!(teams("8"), Ok(8));
+    assert!(teams("1").is_err());
+    assert!(teams("9").is_err());
+    assert_eq!(ConnectionSettings::default().teams, 0);
+}
+
+#[test]
+fn spawns_avoid_enemies_only() {
+    let mut lobby = Lobby {
+        teams: 2,
+        ..Default::default()
+    };
+    join(&mut lobby, 0..4);
+    for id in 0..4 {
+        let player = lobby.players.get_mut(&ClientId::from_raw(id)).unwrap();
+        player.position.translation = Vec3::X * id as f32;
+    }
+    // Les joueurs 0 et 2 sont dans l'equipe 0, 1 et 3 dans l'equipe 1
+    let mut enemies = lobby.enemy_positions(ClientId::from_raw(0), Some(0));
+    enemies.sort_by(|a, b| a.x.total_cmp(&b.x));
+    assert_eq!(enemies, [Vec3::X, Vec3::X * 3.0]);
+    // Chacun pour soi : tous les autres
+    assert_eq!(lobby.enemy_positions(ClientId::from_raw(0), None).len(), 3);
+}
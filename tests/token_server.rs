@@ -0,0 +1,51 @@
+//! Le serveur de tokens ne donne un token qu'avec le mot de passe de la partie.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use game_test::auth::{self, TokenServer};
+use game_test::username;
+
+fn spawn_token_server(password: &str) -> SocketAddr {
+    let game_server: SocketAddr = (Ipv4Addr::LOCALHOST, 5000).into();
+    let token_server = TokenServer::bind(
+        (Ipv4Addr::LOCALHOST, 0).into(),
+        auth::random_private_key(),
+        auth::parse_password(password).unwrap(),
+        vec![game_server],
+    )
+    .unwrap();
+    let addr = token_server.local_addr().unwrap();
+    std::thread::spawn(move || token_server.run());
+    addr
+}
+
+#[test]
+fn right_password_gets_a_token() {
+    let addr = spawn_token_server("hunter2");
+    let token = auth::request_connect_token(
+        addr,
+        &username::to_user_data("Player"),
+        &auth::parse_password("hunter2").unwrap(),
+    );
+    assert!(token.is_ok());
+}
+
+#[test]
+fn wrong_password_is_refused() {
+    let addr = spawn_token_server("hunter2");
+    let error = auth::request_connect_token(
+        addr,
+        &username::to_user_data("Player"),
+        &auth::parse_password("hunter3").unwrap(),
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn password_length_is_checked() {
+    assert!(auth::parse_password("").is_err());
+    assert!(auth::parse_password(&"x".repeat(auth::PASSWORD_BYTES + 1)).is_err());
+    assert!(auth::parse_password(&"x".repeat(auth::PASSWORD_BYTES)).is_ok());
+}
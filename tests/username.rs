@@ -0,0 +1,63 @@
+//! Pseudo transmis dans le `user_data` du netcode et valide par le serveur.
+
+use game_test::username::{
+    from_user_data, make_unique, to_user_data, validate, UsernameError, MAX_USERNAME_LEN,
+};
+
+#[test]
+fn user_data_round_trip() {
+    assert_eq!(
+        from_user_data(&to_user_data("Kévin_42")).as_deref(),
+        Some("Kévin_42")
+    );
+    // Un client sans pseudo envoie des zeros
+    assert_eq!(from_user_data(&[0; 256]).as_deref(), Some(""));
+}
+
+#[test]
+fn invalid_utf8_is_rejected() {
+    let mut user_data = to_user_data("ab");
+    user_data[1] = 0xff;
+    assert_eq!(from_user_data(&user_data), None);
+}
+
+#[test]
+fn validation_trims_and_checks_length_and_characters() {
+    assert_eq!(validate("  Bob  "), Ok("Bob".to_string()));
+    assert_eq!(validate("   "), Err(UsernameError::Empty));
+    assert_eq!(
+        validate(&"a".repeat(MAX_USERNAME_LEN + 1)),
+        Err(UsernameError::TooLong)
+    );
+    assert_eq!(
+        validate("Bob<script>"),
+        Err(UsernameError::InvalidCharacter('<'))
+    );
+    assert_eq!(validate("Bob-the_2nd"), Ok("Bob-the_2nd".to_string()));
+}
+
+#[test]
+fn lookalike_names_are_rejected() {
+    // "a" cyrillique, accent, espace insecable et espaces qui imitent un autre pseudo
+    assert_eq!(
+        validate("B\u{0430}b"),
+        Err(UsernameError::InvalidCharacter('\u{0430}'))
+    );
+    assert_eq!(validate("Kévin"), Err(UsernameError::InvalidCharacter('é')));
+    assert_eq!(
+        validate("Bo\u{00a0}b"),
+        Err(UsernameError::InvalidCharacter('\u{00a0}'))
+    );
+    assert_eq!(validate("B ob"), Err(UsernameError::InvalidCharacter(' ')));
+}
+
+#[test]
+fn taken_names_get_a_suffix() {
+    let taken = ["Bob", "bob2", "Alice"];
+    assert_eq!(make_unique("Carol", taken.iter().copied()), "Carol");
+    assert_eq!(make_unique("BOB", taken.iter().copied()), "BOB3");
+    // Le numero remplace la fin d'un pseudo deja a la longueur maximale
+    let long = "a".repeat(MAX_USERNAME_LEN);
+    let unique = make_unique(&long, [long.as_str()].into_iter());
+    assert_eq!(unique, format!("{}2", "a".repeat(MAX_USERNAME_LEN - 1)));
+}
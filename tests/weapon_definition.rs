@@ -0,0 +1,93 @@
+//! Fichiers d'armes de `assets/weapons/`, lus par le serveur et les clients.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use game_test::inventory::LOADOUT;
+use game_test::weapon::DEFAULT_WEAPON;
+use game_test::weapon_definition::{WeaponDefinition, WeaponLoadError, WEAPONS_FOLDER};
+
+fn definitions() -> Vec<WeaponDefinition> {
+    let folder = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(WEAPONS_FOLDER);
+    fs::read_dir(folder)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            WeaponDefinition::from_ron(&fs::read(&path).unwrap())
+                .unwrap_or_else(|error| panic!("{}: {}", path.display(), error))
+        })
+        .collect()
+}
+
+#[test]
+fn every_weapon_file_parses_with_a_unique_id() {
+    let definitions = definitions();
+    let ids: HashSet<_> = definitions.iter().map(|definition| definition.id).collect();
+    assert_eq!(ids.len(), definitions.len());
+    assert!(ids.contains(&DEFAULT_WEAPON));
+    for weapon in LOADOUT {
+        assert!(ids.contains(&weapon), "no file for weapon {}", weapon);
+    }
+}
+
+#[test]
+fn weapon_values_are_usable() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    for definition in definitions() {
+        assert!(definition.fire_interval > 0.0, "{}", definition.name);
+        assert!(definition.projectile_speed > 0.0, "{}", definition.name);
+        assert!(definition.damage > 0.0, "{}", definition.name);
+        assert!(definition.spread >= 0.0, "{}", definition.name);
+        assert!(definition.magazine_size > 0, "{}", definition.name);
+        assert!(definition.reload_time > 0.0, "{}", definition.name);
+        assert!(definition.equip_time > 0.0, "{}", definition.name);
+        for sound in [&definition.sound, &definition.dry_fire_sound] {
+            assert!(root.join(sound).exists(), "{}", sound);
+        }
+    }
+}
+
+#[test]
+fn missing_field_is_an_error() {
+    assert!(WeaponDefinition::from_ron(b"(id: 1, name: \"Incomplete\")").is_err());
+}
+
+/// Le blaster livre, avec `field` remplace par `value`
+fn blaster_with(field: &str, value: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(WEAPONS_FOLDER)
+        .join("blaster.weapon.ron");
+    let text = fs::read_to_string(path).unwrap();
+    let line = text
+        .lines()
+        .find(|line| line.trim_start().starts_with(&format!("{}:", field)))
+        .unwrap();
+    text.replace(line, &format!("    {}: {},", field, value))
+        .into_bytes()
+}
+
+#[test]
+fn unusable_values_are_rejected() {
+    assert!(WeaponDefinition::from_ron(&blaster_with("fire_interval", "0.08")).is_ok());
+    for (field, value) in [
+        ("fire_interval", "-0.1"),
+        ("fire_interval", "NaN"),
+        ("projectile_speed", "0.0"),
+        ("projectile_lifetime", "inf"),
+        ("reload_time", "-1.0"),
+        ("equip_time", "0.0"),
+        ("spread", "-0.01"),
+        ("spread", "NaN"),
+        ("magazine_size", "0"),
+    ] {
+        let error = WeaponDefinition::from_ron(&blaster_with(field, value));
+        assert!(
+            matches!(error, Err(WeaponLoadError::Invalid(_))),
+            "{}: {}",
+            field,
+            value
+        );
+    }
+}